@@ -0,0 +1,212 @@
+use ethers::{
+    abi::{
+        param_type::Reader,
+        token::{LenientTokenizer, Tokenizer},
+        Function, Param, ParamType, StateMutability, Token,
+    },
+    types::I256,
+    utils::{hex, to_checksum},
+};
+use ethers_core::utils::parse_units;
+use serde_json::{json, Value};
+
+use crate::{error::Error, types::zkevm_types};
+
+fn abi_error(message: String) -> Error {
+    ethers::abi::Error::Other(message.into()).into()
+}
+
+/// Parses a parenthesized, comma separated list of types, e.g. `(address,uint256)`
+fn parse_types(types: &str) -> Result<Vec<ParamType>, Error> {
+    if types.trim() == "()" {
+        return Ok(Vec::new());
+    }
+    match Reader::read(types)? {
+        ParamType::Tuple(types) => Ok(types),
+        _ => Err(abi_error(format!("expected a type list, got {types}"))),
+    }
+}
+
+fn params(types: Vec<ParamType>) -> Vec<Param> {
+    types
+        .into_iter()
+        .map(|kind| Param {
+            name: String::new(),
+            kind,
+            internal_type: None,
+        })
+        .collect()
+}
+
+/// Parses a signature with optional return types, like `balanceOf(address)(uint256)`
+pub fn parse_signature(signature: &str) -> Result<Function, Error> {
+    let signature = signature.trim();
+    let open = signature
+        .find('(')
+        .ok_or_else(|| abi_error(format!("missing parameters in {signature}")))?;
+    let name = &signature[..open];
+    if name.is_empty() {
+        return Err(abi_error(format!("missing function name in {signature}")));
+    }
+
+    // find the parenthesis closing the parameter list
+    let mut depth = 0;
+    let mut close = None;
+    for (index, char) in signature[open..].char_indices() {
+        match char {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open + index);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close.ok_or_else(|| abi_error(format!("unbalanced parentheses in {signature}")))?;
+    let inputs = parse_types(&signature[open..=close])?;
+    let outputs = match &signature[close + 1..] {
+        "" => Vec::new(),
+        outputs => parse_types(outputs)?,
+    };
+
+    #[allow(deprecated)]
+    Ok(Function {
+        name: name.to_string(),
+        inputs: params(inputs),
+        outputs: params(outputs),
+        constant: None,
+        state_mutability: StateMutability::View,
+    })
+}
+
+/// Encodes calldata from human readable arguments, one per function input
+pub fn encode_call(function: &Function, args: &[String]) -> Result<zkevm_types::Bytes, Error> {
+    if args.len() != function.inputs.len() {
+        return Err(abi_error(format!(
+            "{} expects {} arguments, got {}",
+            function.signature(),
+            function.inputs.len(),
+            args.len()
+        )));
+    }
+    let tokens = function
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| LenientTokenizer::tokenize(&param.kind, arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(function.encode_input(&tokens)?.into())
+}
+
+/// Parses an amount of ether, in wei unless followed by a unit, e.g. `100`,
+/// `0x64`, `1.5ether` or `10 gwei`
+pub fn parse_value(value: &str) -> Result<zkevm_types::Word, Error> {
+    let invalid = |err: String| Error::config(format!("invalid value {value}: {err}"));
+    let trimmed = value.trim();
+    if let Some(hex) = trimmed.strip_prefix("0x") {
+        return zkevm_types::Word::from_str_radix(hex, 16).map_err(|err| invalid(err.to_string()));
+    }
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let (amount, unit) = trimmed.split_at(split);
+    let unit = match unit.trim() {
+        "" => "wei",
+        unit => unit,
+    };
+    parse_units(amount, unit)
+        .map(Into::into)
+        .map_err(|err| invalid(err.to_string()))
+}
+
+pub fn decode_output(function: &Function, data: &[u8]) -> Result<Vec<Token>, Error> {
+    Ok(function.decode_output(data)?)
+}
+
+/// JSON representation of decoded values, integers are decimal strings as
+/// they may not fit in a JSON number
+pub fn token_to_json(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => json!(to_checksum(address, None)),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => {
+            json!(format!("0x{}", hex::encode(bytes)))
+        }
+        Token::Int(value) => json!(I256::from_raw(*value).to_string()),
+        Token::Uint(value) => json!(value.to_string()),
+        Token::Bool(value) => json!(value),
+        Token::String(value) => json!(value),
+        Token::FixedArray(tokens) | Token::Array(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(token_to_json).collect())
+        }
+    }
+}
+
+pub fn tokens_to_json(tokens: &[Token]) -> Value {
+    Value::Array(tokens.iter().map(token_to_json).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_output, encode_call, parse_signature, parse_value, tokens_to_json};
+    use crate::types::zkevm_types::Word;
+    use ethers::abi::{encode, Token};
+    use serde_json::json;
+
+    #[test]
+    fn test_signature() {
+        let function = parse_signature("balanceOf(address)(uint256)").unwrap();
+        assert_eq!(function.signature(), "balanceOf(address):(uint256)");
+        assert_eq!(function.short_signature(), [0x70, 0xa0, 0x82, 0x31]);
+
+        let function = parse_signature("swap((address,uint256)[],bool)").unwrap();
+        assert_eq!(function.inputs.len(), 2);
+        assert!(function.outputs.is_empty());
+
+        let function = parse_signature("name()(string)").unwrap();
+        assert!(function.inputs.is_empty());
+
+        assert!(parse_signature("balanceOf").is_err());
+        assert!(parse_signature("balanceOf(address").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let function = parse_signature("balanceOf(address)(uint256,int8,bool)").unwrap();
+        let calldata = encode_call(
+            &function,
+            &["0x35c6ace6404d8fd1cee19026b3d56d0c9627a646".to_string()],
+        )
+        .unwrap();
+        assert_eq!(calldata.len(), 4 + 32);
+        assert_eq!(&calldata[..4], &[0x70, 0xa0, 0x82, 0x31]);
+        assert!(encode_call(&function, &[]).is_err());
+
+        let return_data = encode(&[
+            Token::Uint(42.into()),
+            Token::Int(ethers::types::I256::minus_one().into_raw()),
+            Token::Bool(true),
+        ]);
+        let tokens = decode_output(&function, &return_data).unwrap();
+        assert_eq!(tokens_to_json(&tokens), json!(["42", "-1", true]));
+        assert!(decode_output(&function, &[0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("100").unwrap(), Word::from(100));
+        assert_eq!(parse_value("0x64").unwrap(), Word::from(100));
+        assert_eq!(
+            parse_value("10 gwei").unwrap(),
+            Word::from(10_000_000_000u64)
+        );
+        assert_eq!(
+            parse_value("1.5ether").unwrap(),
+            Word::from(1_500_000_000_000_000_000u64)
+        );
+        assert!(parse_value("1.5").is_err());
+        assert!(parse_value("1 bitcoin").is_err());
+    }
+}
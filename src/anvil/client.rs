@@ -1,36 +1,108 @@
-use std::{thread::sleep, time::Duration};
+use std::{net::SocketAddr, time::Duration};
 
 use crate::{
-    anvil::conversion::{convert_option, ConversionReverse},
+    anvil::conversion::{
+        convert_option, patch_trace, patch_transaction, ConversionReverse, TryConversion,
+    },
     env::Env,
     error::Error,
+    overrides::StateOverride,
+    trace::TraceFrame,
     types::{anvil_types, zkevm_types},
 };
-use anvil::{eth::EthApi, spawn, NodeConfig};
-use ethers::utils::parse_ether;
+use anvil::{eth::EthApi, spawn, NodeHandle};
+use tracing::{instrument, warn};
+
+use super::{
+    config::{AnvilClientConfig, MiningMode},
+    conversion::Conversion,
+};
+
+/// Controls how `AnvilClient::wait_for_transaction` polls for a receipt
+#[derive(Clone, Copy, Debug)]
+pub struct WaitOptions {
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    /// mine a block on every poll, for nodes which are not automining
+    pub auto_mine: bool,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(100),
+            auto_mine: false,
+        }
+    }
+}
+
+impl WaitOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_auto_mine(mut self, auto_mine: bool) -> Self {
+        self.auto_mine = auto_mine;
+        self
+    }
+}
 
-use super::conversion::Conversion;
 pub struct AnvilClient {
     eth_api: EthApi,
+    node_handle: NodeHandle,
 }
 
-#[allow(dead_code)]
 impl AnvilClient {
-    pub async fn default() -> Self {
-        let env = Env::load();
-        Self::setup(env.eth_rpc_url, env.fork_block_number).await
+    pub async fn default() -> Result<Self, Error> {
+        let env = Env::load()?;
+        Ok(Self::setup(env.eth_rpc_url, env.fork_block_number).await)
     }
 
+    /// Spawns anvil on an OS-assigned port, see `socket_address` for the bound address
     pub async fn setup(eth_rpc_url: Option<String>, fork_block_number: Option<usize>) -> Self {
-        let node_config = NodeConfig::default()
-            .with_eth_rpc_url(eth_rpc_url)
-            .with_fork_block_number(fork_block_number.map(|v| v as u64))
-            .with_port(8548)
-            .silent()
-            .with_steps_tracing(true);
+        Self::setup_with_port(eth_rpc_url, fork_block_number, 0).await
+    }
+
+    pub async fn setup_with_port(
+        eth_rpc_url: Option<String>,
+        fork_block_number: Option<usize>,
+        port: u16,
+    ) -> Self {
+        Self::setup_with_config(
+            &AnvilClientConfig::default()
+                .with_eth_rpc_url(eth_rpc_url)
+                .with_fork_block_number(fork_block_number)
+                .with_port(port),
+        )
+        .await
+    }
 
-        let (eth_api, _) = spawn(node_config).await;
-        Self { eth_api }
+    #[instrument(
+        name = "fork_setup",
+        skip_all,
+        fields(fork_block_number = config.fork_block_number, port = config.port)
+    )]
+    pub async fn setup_with_config(config: &AnvilClientConfig) -> Self {
+        let (eth_api, node_handle) = spawn(config.to_node_config()).await;
+        Self {
+            eth_api,
+            node_handle,
+        }
+    }
+
+    pub fn socket_address(&self) -> SocketAddr {
+        *self.node_handle.socket_address()
+    }
+
+    pub fn http_endpoint(&self) -> String {
+        self.node_handle.http_endpoint()
     }
 
     pub fn eth_chain_id(&self) -> Result<Option<zkevm_types::Word>, Error> {
@@ -118,9 +190,9 @@ impl AnvilClient {
             .get_proof(
                 address.to_anvil_type(),
                 keys.iter().map(|key| key.to_anvil_type()).collect(),
-                block_number.map(|_block_number| {
+                block_number.map(|block_number| {
                     anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
+                        anvil_types::U64::from(block_number),
                     ))
                 }),
             )
@@ -137,19 +209,59 @@ impl AnvilClient {
         ))
     }
 
-    pub async fn debug_trace_transaction(
+    pub async fn debug_trace_transaction_frame(
         &self,
         hash: zkevm_types::Hash,
         options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<TraceFrame, Error> {
+        Ok(self
+            .eth_api
+            .debug_trace_transaction(hash.to_anvil_type(), options)
+            .await?
+            .try_to_zkevm_type()?)
+    }
+
+    pub async fn debug_trace_call(
+        &self,
+        request: anvil_types::EthTransactionRequest,
+        block_number: Option<usize>,
+        options: anvil_types::GethDebugTracingOptions,
     ) -> Result<zkevm_types::GethExecTrace, Error> {
         let trace = self
             .eth_api
-            .debug_trace_transaction(hash.to_anvil_type(), options)
+            .debug_trace_call(
+                request,
+                block_number.map(|block_number| {
+                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
+                        anvil_types::U64::from(block_number),
+                    ))
+                }),
+                options,
+            )
             .await?
-            .to_zkevm_type();
+            .try_to_zkevm_type()?;
         Ok(patch_trace(trace))
     }
 
+    pub async fn estimate_gas(
+        &self,
+        request: anvil_types::EthTransactionRequest,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::U256, Error> {
+        Ok(self
+            .eth_api
+            .estimate_gas(
+                request,
+                block_number.map(|block_number| {
+                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
+                        anvil_types::U64::from(block_number),
+                    ))
+                }),
+            )
+            .await?
+            .to_zkevm_type())
+    }
+
     pub async fn get_code(
         &self,
         address: zkevm_types::Address,
@@ -159,9 +271,9 @@ impl AnvilClient {
             .eth_api
             .get_code(
                 address.to_anvil_type(),
-                block_number.map(|_block_number| {
+                block_number.map(|block_number| {
                     anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
+                        anvil_types::U64::from(block_number),
                     ))
                 }),
             )
@@ -180,6 +292,75 @@ impl AnvilClient {
             .await?)
     }
 
+    pub async fn set_balance(
+        &self,
+        address: zkevm_types::Address,
+        balance: zkevm_types::U256,
+    ) -> Result<(), Error> {
+        Ok(self
+            .eth_api
+            .anvil_set_balance(address.to_anvil_type(), balance.to_anvil_type())
+            .await?)
+    }
+
+    pub async fn set_nonce(
+        &self,
+        address: zkevm_types::Address,
+        nonce: zkevm_types::U64,
+    ) -> Result<(), Error> {
+        Ok(self
+            .eth_api
+            .anvil_set_nonce(
+                address.to_anvil_type(),
+                anvil_types::U256::from(nonce.as_u64()),
+            )
+            .await?)
+    }
+
+    pub async fn set_storage_at(
+        &self,
+        address: zkevm_types::Address,
+        index: zkevm_types::U256,
+        value: zkevm_types::H256,
+    ) -> Result<(), Error> {
+        self.eth_api
+            .anvil_set_storage_at(
+                address.to_anvil_type(),
+                index.to_anvil_type(),
+                value.to_anvil_type(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Applies the override set to the current state, which calls and proofs
+    /// at the latest block see without mining. The latest block's header and
+    /// state root are left as they were.
+    pub async fn apply_state_override(&self, state_override: &StateOverride) -> Result<(), Error> {
+        for (address, account) in &state_override.0 {
+            if account.state.is_some() {
+                return Err(
+                    Error::config("full storage override is not supported, use stateDiff")
+                        .with_address(*address),
+                );
+            }
+            if let Some(balance) = account.balance {
+                self.set_balance(*address, balance).await?;
+            }
+            if let Some(nonce) = account.nonce {
+                self.set_nonce(*address, nonce).await?;
+            }
+            if let Some(code) = &account.code {
+                self.set_code(*address, code.clone()).await?;
+            }
+            for (slot, value) in account.state_diff.iter().flatten() {
+                self.set_storage_at(*address, zkevm_types::h256_to_u256(*slot), *value)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
     pub async fn get_balance(
         &self,
         address: zkevm_types::Address,
@@ -189,9 +370,9 @@ impl AnvilClient {
             .eth_api
             .balance(
                 address.to_anvil_type(),
-                block_number.map(|_block_number| {
+                block_number.map(|block_number| {
                     anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
+                        anvil_types::U64::from(block_number),
                     ))
                 }),
             )
@@ -208,9 +389,9 @@ impl AnvilClient {
             .eth_api
             .transaction_count(
                 address.to_anvil_type(),
-                block_number.map(|_block_number| {
+                block_number.map(|block_number| {
                     anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
+                        anvil_types::U64::from(block_number),
                     ))
                 }),
             )
@@ -229,9 +410,9 @@ impl AnvilClient {
             .storage_at(
                 address.to_anvil_type(),
                 index.to_anvil_type(),
-                block_number.map(|_block_number| {
+                block_number.map(|block_number| {
                     anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
+                        anvil_types::U64::from(block_number),
                     ))
                 }),
             )
@@ -254,17 +435,22 @@ impl AnvilClient {
         &self,
         address: zkevm_types::Address,
     ) -> Result<zkevm_types::Hash, Error> {
-        let accounts = self.eth_api.accounts().unwrap();
+        let from = self
+            .eth_api
+            .accounts()?
+            .first()
+            .copied()
+            .ok_or_else(|| Error::rpc("no dev accounts to fund from"))?;
         Ok(self
             .eth_api
             .send_transaction(anvil_types::EthTransactionRequest {
-                from: Some(accounts[0]),
+                from: Some(from),
                 to: Some(address.to_anvil_type()),
                 gas_price: None,
                 max_fee_per_gas: None,
                 max_priority_fee_per_gas: None,
                 gas: None,
-                value: Some(parse_ether("1").unwrap()),
+                value: Some(anvil_types::U256::exp10(18)),
                 data: None,
                 nonce: None,
                 chain_id: None,
@@ -275,47 +461,186 @@ impl AnvilClient {
             .to_zkevm_type())
     }
 
-    pub async fn mine_one(&self) {
-        self.eth_api.mine_one().await;
+    pub async fn snapshot(&self) -> Result<zkevm_types::U256, Error> {
+        Ok(self.eth_api.evm_snapshot().await?.to_zkevm_type())
     }
 
-    pub async fn wait_for_transaction(&self, hash: zkevm_types::Hash) -> Result<(), Error> {
-        loop {
-            let rc = self.transaction_receipt(hash).await.unwrap();
-            if rc.is_some() {
-                return Ok(());
+    /// Reverts the state to the given snapshot, the snapshot is consumed
+    pub async fn revert(&self, snapshot_id: zkevm_types::U256) -> Result<(), Error> {
+        if self.eth_api.evm_revert(snapshot_id.to_anvil_type()).await? {
+            Ok(())
+        } else {
+            Err(Error::rpc(format!("snapshot {snapshot_id} not found")))
+        }
+    }
+
+    /// Reverts to the snapshot once `result` was computed on top of it. If both
+    /// fail the error of `result` is returned and the failed revert only logged.
+    pub async fn revert_after<T>(
+        &self,
+        snapshot_id: zkevm_types::U256,
+        result: Result<T, Error>,
+    ) -> Result<T, Error> {
+        match (self.revert(snapshot_id).await, result) {
+            (Ok(()), result) => result,
+            (Err(err), Ok(_)) => Err(err),
+            (Err(revert_err), Err(err)) => {
+                warn!(%revert_err, %snapshot_id, "cannot revert to snapshot");
+                Err(err)
             }
-            sleep(Duration::from_secs(1))
         }
     }
-}
 
-pub fn patch_transaction(mut tx: zkevm_types::Transaction) -> zkevm_types::Transaction {
-    if tx.transaction_type.is_none() {
-        tx.transaction_type = Some(zkevm_types::U64::from(0));
-        tx.max_fee_per_gas = None;
-        tx.max_priority_fee_per_gas = None;
+    /// Re-forks at the given block, dropping all local state
+    pub async fn reset_fork(
+        &self,
+        eth_rpc_url: Option<String>,
+        block_number: Option<usize>,
+    ) -> Result<(), Error> {
+        Ok(self
+            .eth_api
+            .anvil_reset(Some(anvil_types::Forking {
+                json_rpc_url: eth_rpc_url,
+                block_number: block_number.map(|v| v as u64),
+            }))
+            .await?)
     }
-    tx
-}
 
-pub fn patch_trace(mut trace: zkevm_types::GethExecTrace) -> zkevm_types::GethExecTrace {
-    if trace.struct_logs.len() == 1 && trace.struct_logs[0].op == zkevm_types::OpcodeId::STOP {
-        trace.struct_logs = vec![]
+    pub async fn mine_one(&self) {
+        self.eth_api.mine_one().await;
+    }
+
+    pub async fn set_mining_mode(&self, mining_mode: MiningMode) -> Result<(), Error> {
+        match mining_mode {
+            MiningMode::Auto => {
+                self.eth_api.anvil_set_interval_mining(0)?;
+                self.eth_api.anvil_set_auto_mine(true).await?;
+            }
+            MiningMode::Interval(interval) => {
+                // anvil only takes whole seconds here, anything else would be
+                // silently rounded
+                if interval.is_zero() || interval.subsec_nanos() != 0 {
+                    return Err(Error::config(format!(
+                        "mining interval must be a whole number of seconds, got {interval:?}"
+                    )));
+                }
+                self.eth_api.anvil_set_auto_mine(false).await?;
+                self.eth_api.anvil_set_interval_mining(interval.as_secs())?;
+            }
+            MiningMode::Manual => {
+                self.eth_api.anvil_set_auto_mine(false).await?;
+                self.eth_api.anvil_set_interval_mining(0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn wait_for_transaction(
+        &self,
+        hash: zkevm_types::Hash,
+        options: WaitOptions,
+    ) -> Result<zkevm_types::TransactionReceipt, Error> {
+        let wait = async {
+            loop {
+                if let Some(rc) = self.transaction_receipt(hash).await? {
+                    return Ok::<_, Error>(rc);
+                }
+                if options.auto_mine {
+                    self.mine_one().await;
+                }
+                tokio::time::sleep(options.poll_interval).await;
+            }
+        };
+        tokio::time::timeout(options.timeout, wait)
+            .await
+            .map_err(|_| Error::TimeoutError(options.timeout))?
     }
-    trace
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::anvil::AnvilClient;
+    use std::time::Duration;
+
+    use crate::anvil::{AnvilClient, MiningMode, WaitOptions};
+    use crate::error::Error;
+    use crate::types::zkevm_types::{Address, Word};
 
-    // ignored because cannot run anvil again in other test
-    #[ignore]
     #[tokio::test]
     async fn test() {
         let cli = AnvilClient::setup(None, None).await;
         let bn = cli.block_number().unwrap();
         assert_eq!(bn, 0);
     }
+
+    #[tokio::test]
+    async fn test_multiple_instances() {
+        let cli_a = AnvilClient::setup(None, None).await;
+        let cli_b = AnvilClient::setup(None, None).await;
+        assert_ne!(cli_a.socket_address().port(), 0);
+        assert_ne!(cli_a.socket_address(), cli_b.socket_address());
+    }
+
+    #[tokio::test]
+    async fn test_mining_modes() {
+        let cli = AnvilClient::setup(None, None).await;
+        let to = Address::repeat_byte(0x30);
+        let short_wait = WaitOptions::default().with_timeout(Duration::from_millis(500));
+
+        cli.set_mining_mode(MiningMode::Manual).await.unwrap();
+        let hash = cli.fund_wallet(to).await.unwrap();
+        assert!(cli.wait_for_transaction(hash, short_wait).await.is_err());
+        cli.mine_one().await;
+        assert!(cli.transaction_receipt(hash).await.unwrap().is_some());
+
+        cli.set_mining_mode(MiningMode::Auto).await.unwrap();
+        let hash = cli.fund_wallet(to).await.unwrap();
+        cli.wait_for_transaction(hash, short_wait).await.unwrap();
+
+        cli.set_mining_mode(MiningMode::Interval(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        let hash = cli.fund_wallet(to).await.unwrap();
+        assert!(cli.transaction_receipt(hash).await.unwrap().is_none());
+        cli.wait_for_transaction(
+            hash,
+            WaitOptions::default().with_timeout(Duration::from_secs(5)),
+        )
+        .await
+        .unwrap();
+
+        for interval in [
+            Duration::from_millis(500),
+            Duration::from_millis(1500),
+            Duration::ZERO,
+        ] {
+            assert!(cli
+                .set_mining_mode(MiningMode::Interval(interval))
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_revert() {
+        let cli = AnvilClient::setup(None, None).await;
+        let address = Address::repeat_byte(0x33);
+
+        let snapshot_id = cli.snapshot().await.unwrap();
+        cli.set_balance(address, Word::from(1)).await.unwrap();
+        cli.mine_one().await;
+        assert_eq!(cli.get_balance(address, None).await.unwrap(), Word::from(1));
+        assert_eq!(cli.block_number().unwrap(), 1);
+
+        cli.revert(snapshot_id).await.unwrap();
+        assert_eq!(cli.get_balance(address, None).await.unwrap(), Word::zero());
+        assert_eq!(cli.block_number().unwrap(), 0);
+        // a snapshot is consumed by reverting to it
+        assert!(cli.revert(snapshot_id).await.is_err());
+
+        // the error of the reverted work is kept over the failed revert
+        let result: Result<(), Error> = Err(Error::witness("call failed"));
+        let err = cli.revert_after(snapshot_id, result).await.unwrap_err();
+        assert_eq!(err.to_string(), "witness error: call failed");
+        assert!(cli.revert_after(snapshot_id, Ok(())).await.is_err());
+    }
 }
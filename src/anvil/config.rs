@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use anvil::{Hardfork, NodeConfig};
+use ethers::signers::LocalWallet;
+
+use crate::types::anvil_types;
+
+/// How anvil mines new blocks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MiningMode {
+    /// mine a block as soon as a transaction is received (anvil's default)
+    Auto,
+    /// mine a block at a fixed interval, `AnvilClient::set_mining_mode` only
+    /// takes whole seconds
+    Interval(Duration),
+    /// only mine when explicitly asked to, see `AnvilClient::mine_one`
+    Manual,
+}
+
+/// Settings for the embedded anvil node, maps onto anvil's `NodeConfig`
+#[derive(Clone, Debug, Default)]
+pub struct AnvilClientConfig {
+    pub eth_rpc_url: Option<String>,
+    pub fork_block_number: Option<usize>,
+    /// `0` lets the OS pick a free port
+    pub port: u16,
+    pub chain_id: Option<u64>,
+    pub hardfork: Option<Hardfork>,
+    pub gas_limit: Option<u64>,
+    pub base_fee: Option<u64>,
+    pub genesis_timestamp: Option<u64>,
+    pub genesis_accounts: Vec<LocalWallet>,
+    pub genesis_balance: Option<anvil_types::U256>,
+    pub fork_request_timeout: Option<Duration>,
+    pub fork_request_retries: Option<u32>,
+    pub fork_retry_backoff: Option<Duration>,
+    pub compute_units_per_second: Option<u64>,
+    pub mining_mode: Option<MiningMode>,
+}
+
+impl AnvilClientConfig {
+    pub fn with_eth_rpc_url(mut self, eth_rpc_url: Option<String>) -> Self {
+        self.eth_rpc_url = eth_rpc_url;
+        self
+    }
+
+    pub fn with_fork_block_number(mut self, fork_block_number: Option<usize>) -> Self {
+        self.fork_block_number = fork_block_number;
+        self
+    }
+
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_chain_id(mut self, chain_id: Option<u64>) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_hardfork(mut self, hardfork: Option<Hardfork>) -> Self {
+        self.hardfork = hardfork;
+        self
+    }
+
+    pub fn with_gas_limit(mut self, gas_limit: Option<u64>) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn with_base_fee(mut self, base_fee: Option<u64>) -> Self {
+        self.base_fee = base_fee;
+        self
+    }
+
+    pub fn with_genesis_timestamp(mut self, genesis_timestamp: Option<u64>) -> Self {
+        self.genesis_timestamp = genesis_timestamp;
+        self
+    }
+
+    pub fn with_genesis_accounts(mut self, genesis_accounts: Vec<LocalWallet>) -> Self {
+        self.genesis_accounts = genesis_accounts;
+        self
+    }
+
+    pub fn with_genesis_balance(mut self, genesis_balance: Option<anvil_types::U256>) -> Self {
+        self.genesis_balance = genesis_balance;
+        self
+    }
+
+    pub fn with_fork_request_timeout(mut self, fork_request_timeout: Option<Duration>) -> Self {
+        self.fork_request_timeout = fork_request_timeout;
+        self
+    }
+
+    pub fn with_fork_request_retries(mut self, fork_request_retries: Option<u32>) -> Self {
+        self.fork_request_retries = fork_request_retries;
+        self
+    }
+
+    pub fn with_fork_retry_backoff(mut self, fork_retry_backoff: Option<Duration>) -> Self {
+        self.fork_retry_backoff = fork_retry_backoff;
+        self
+    }
+
+    pub fn with_compute_units_per_second(mut self, compute_units_per_second: Option<u64>) -> Self {
+        self.compute_units_per_second = compute_units_per_second;
+        self
+    }
+
+    pub fn with_mining_mode(mut self, mining_mode: Option<MiningMode>) -> Self {
+        self.mining_mode = mining_mode;
+        self
+    }
+
+    pub fn to_node_config(&self) -> NodeConfig {
+        let mut node_config = NodeConfig::default()
+            .with_eth_rpc_url(self.eth_rpc_url.clone())
+            .with_fork_block_number(self.fork_block_number.map(|v| v as u64))
+            .with_port(self.port)
+            .with_chain_id(self.chain_id)
+            .with_hardfork(self.hardfork)
+            .with_gas_limit(self.gas_limit)
+            .with_base_fee(self.base_fee)
+            .with_genesis_timestamp(self.genesis_timestamp)
+            .fork_request_timeout(self.fork_request_timeout)
+            .fork_request_retries(self.fork_request_retries)
+            .fork_retry_backoff(self.fork_retry_backoff)
+            .fork_compute_units_per_second(self.compute_units_per_second)
+            .silent()
+            .with_steps_tracing(true);
+        if !self.genesis_accounts.is_empty() {
+            node_config = node_config.with_genesis_accounts(self.genesis_accounts.clone());
+        }
+        if let Some(genesis_balance) = self.genesis_balance {
+            node_config = node_config.with_genesis_balance(genesis_balance);
+        }
+        match self.mining_mode {
+            Some(MiningMode::Interval(interval)) => {
+                node_config = node_config.with_blocktime(Some(interval));
+            }
+            Some(MiningMode::Manual) => {
+                node_config = node_config.with_no_mining(true);
+            }
+            Some(MiningMode::Auto) | None => {}
+        }
+        node_config
+    }
+}
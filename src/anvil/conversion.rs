@@ -1,6 +1,10 @@
 use std::{collections::HashMap, str::FromStr};
 
-use crate::types::{anvil_types, zkevm_types};
+use crate::{
+    error::Error,
+    trace::{CallTrace, PrestateAccount, PrestateTrace, TraceFrame},
+    types::{anvil_types, zkevm_types},
+};
 
 use ethers::{types::BigEndianHash, utils::hex};
 
@@ -9,10 +13,32 @@ pub trait Conversion<T> {
     fn to_zkevm_type(&self) -> T;
 }
 
+// Fallible conversion from anvil types to zkevm types, for values the zkevm
+// types cannot represent
+pub trait TryConversion<T> {
+    fn try_to_zkevm_type(&self) -> Result<T, Error>;
+}
+
 pub fn convert_option<A: Conversion<Z>, Z>(some_val: Option<A>) -> Option<Z> {
     some_val.map(|val| val.to_zkevm_type())
 }
 
+pub fn patch_transaction(mut tx: zkevm_types::Transaction) -> zkevm_types::Transaction {
+    if tx.transaction_type.is_none() {
+        tx.transaction_type = Some(zkevm_types::U64::from(0));
+        tx.max_fee_per_gas = None;
+        tx.max_priority_fee_per_gas = None;
+    }
+    tx
+}
+
+pub fn patch_trace(mut trace: zkevm_types::GethExecTrace) -> zkevm_types::GethExecTrace {
+    if trace.struct_logs.len() == 1 && trace.struct_logs[0].op == zkevm_types::OpcodeId::STOP {
+        trace.struct_logs = vec![]
+    }
+    trace
+}
+
 impl Conversion<zkevm_types::U256> for anvil_types::U256 {
     fn to_zkevm_type(&self) -> zkevm_types::U256 {
         let mut new = zkevm_types::U256::zero();
@@ -71,6 +97,16 @@ impl Conversion<zkevm_types::Bloom> for anvil_types::Bloom {
     }
 }
 
+impl Conversion<zkevm_types::OtherFields> for anvil_types::OtherFields {
+    fn to_zkevm_type(&self) -> zkevm_types::OtherFields {
+        let mut new = zkevm_types::OtherFields::default();
+        for (key, value) in self.iter() {
+            new.insert(key.clone(), value.clone());
+        }
+        new
+    }
+}
+
 impl Conversion<zkevm_types::AccessList> for anvil_types::AccessList {
     fn to_zkevm_type(&self) -> zkevm_types::AccessList {
         zkevm_types::AccessList(
@@ -114,7 +150,7 @@ impl Conversion<zkevm_types::Transaction> for anvil_types::Transaction {
             max_priority_fee_per_gas: convert_option(self.max_priority_fee_per_gas),
             max_fee_per_gas: convert_option(self.max_fee_per_gas),
             chain_id: convert_option(self.chain_id),
-            other: zkevm_types::OtherFields::default(),
+            other: self.other.to_zkevm_type(),
         }
     }
 }
@@ -148,7 +184,7 @@ impl<A: Conversion<Z>, Z> Conversion<zkevm_types::Block<Z>> for anvil_types::Blo
             mix_hash: convert_option(self.mix_hash),
             nonce: convert_option(self.nonce),
             base_fee_per_gas: convert_option(self.base_fee_per_gas),
-            other: zkevm_types::OtherFields::default(),
+            other: self.other.to_zkevm_type(),
             withdrawals_root: None,
             withdrawals: None,
         }
@@ -173,7 +209,7 @@ impl Conversion<zkevm_types::TransactionReceipt> for anvil_types::TransactionRec
             logs_bloom: self.logs_bloom.to_zkevm_type(),
             transaction_type: convert_option(self.transaction_type),
             effective_gas_price: convert_option(self.effective_gas_price),
-            other: zkevm_types::OtherFields::default(),
+            other: self.other.to_zkevm_type(),
         }
     }
 }
@@ -196,55 +232,138 @@ impl Conversion<zkevm_types::Log> for anvil_types::Log {
     }
 }
 
-impl Conversion<zkevm_types::GethExecTrace> for anvil_types::GethTrace {
-    fn to_zkevm_type(&self) -> zkevm_types::GethExecTrace {
-        if let ethers::types::GethTrace::Known(ethers::types::GethTraceFrame::Default(
-            anvil_trace,
-        )) = self.to_owned()
-        {
-            zkevm_types::GethExecTrace {
-                gas: eth_types::evm_types::Gas(anvil_trace.gas.as_u64()),
-                failed: anvil_trace.failed,
-                return_value: hex::encode(anvil_trace.return_value.as_ref()), // TODO see if 0x adjustment is needed
-                struct_logs: anvil_trace
-                    .struct_logs
-                    .into_iter()
-                    .map(|step| {
-                        zkevm_types::GethExecStep {
-                            pc: eth_types::evm_types::ProgramCounter(step.pc as usize),
-                            op: zkevm_types::OpcodeId::from_str(step.op.as_str()).unwrap(),
-                            gas: eth_types::evm_types::Gas(step.gas),
-                            gas_cost: eth_types::evm_types::GasCost(step.gas_cost),
-                            refund: eth_types::evm_types::Gas(step.refund_counter.unwrap_or(0)),
-                            depth: u16::try_from(step.depth).expect("error converting depth"),
-                            error: step.error,
-                            stack: zkevm_types::Stack(
-                                step.stack
-                                    .unwrap_or(Vec::new())
-                                    .into_iter()
-                                    .map(|w| w.to_zkevm_type())
-                                    .collect(),
-                            ),
-                            memory: zkevm_types::Memory::default(), // memory is not enabled
-                            storage: {
-                                let tree = step.storage.unwrap_or_default();
-                                let mut hash_map =
-                                    HashMap::<zkevm_types::Word, zkevm_types::Word>::new();
-                                for (key, value) in &tree {
-                                    hash_map.insert(key.to_zkevm_type(), value.to_zkevm_type());
-                                }
-                                zkevm_types::Storage(hash_map)
-                            },
-                        }
+impl TryConversion<zkevm_types::GethExecTrace> for ethers::types::DefaultFrame {
+    fn try_to_zkevm_type(&self) -> Result<zkevm_types::GethExecTrace, Error> {
+        let anvil_trace = self.to_owned();
+        Ok(zkevm_types::GethExecTrace {
+            gas: eth_types::evm_types::Gas(anvil_trace.gas.as_u64()),
+            failed: anvil_trace.failed,
+            return_value: hex::encode(anvil_trace.return_value.as_ref()), // TODO see if 0x adjustment is needed
+            struct_logs: anvil_trace
+                .struct_logs
+                .into_iter()
+                .map(|step| {
+                    Ok(zkevm_types::GethExecStep {
+                        pc: eth_types::evm_types::ProgramCounter(step.pc as usize),
+                        op: zkevm_types::OpcodeId::from_str(step.op.as_str()).map_err(|_| {
+                            Error::conversion(format!(
+                                "unknown opcode {} at pc {}",
+                                step.op, step.pc
+                            ))
+                        })?,
+                        gas: eth_types::evm_types::Gas(step.gas),
+                        gas_cost: eth_types::evm_types::GasCost(step.gas_cost),
+                        refund: eth_types::evm_types::Gas(step.refund_counter.unwrap_or(0)),
+                        depth: u16::try_from(step.depth).map_err(|_| {
+                            Error::conversion(format!("call depth {} out of range", step.depth))
+                        })?,
+                        error: step.error,
+                        stack: zkevm_types::Stack(
+                            step.stack
+                                .unwrap_or(Vec::new())
+                                .into_iter()
+                                .map(|w| w.to_zkevm_type())
+                                .collect(),
+                        ),
+                        memory: zkevm_types::Memory::default(), // memory is not enabled
+                        storage: {
+                            let tree = step.storage.unwrap_or_default();
+                            let mut hash_map =
+                                HashMap::<zkevm_types::Word, zkevm_types::Word>::new();
+                            for (key, value) in &tree {
+                                hash_map.insert(key.to_zkevm_type(), value.to_zkevm_type());
+                            }
+                            zkevm_types::Storage(hash_map)
+                        },
                     })
-                    .collect(),
-            }
-        } else {
-            panic!("unknown value in trace")
+                })
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+}
+
+impl Conversion<CallTrace> for ethers::types::CallFrame {
+    fn to_zkevm_type(&self) -> CallTrace {
+        CallTrace {
+            typ: self.typ.clone(),
+            from: self.from.to_zkevm_type(),
+            to: match &self.to {
+                Some(ethers::types::NameOrAddress::Address(address)) => {
+                    Some(address.to_zkevm_type())
+                }
+                _ => None,
+            },
+            value: convert_option(self.value),
+            gas: self.gas.to_zkevm_type(),
+            gas_used: self.gas_used.to_zkevm_type(),
+            input: self.input.to_zkevm_type(),
+            output: self.output.as_ref().map(|output| output.to_zkevm_type()),
+            error: self.error.clone(),
+            calls: self
+                .calls
+                .iter()
+                .flatten()
+                .map(|call| call.to_zkevm_type())
+                .collect(),
+        }
+    }
+}
+
+impl Conversion<PrestateAccount> for ethers::types::AccountState {
+    fn to_zkevm_type(&self) -> PrestateAccount {
+        PrestateAccount {
+            balance: convert_option(self.balance),
+            nonce: convert_option(self.nonce),
+            code: self
+                .code
+                .as_ref()
+                .and_then(|code| zkevm_types::Bytes::from_str(code).ok()),
+            storage: self
+                .storage
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key.to_zkevm_type(), value.to_zkevm_type()))
+                .collect(),
         }
     }
 }
 
+impl Conversion<PrestateTrace> for ethers::types::PreStateFrame {
+    fn to_zkevm_type(&self) -> PrestateTrace {
+        // in diff mode only the state before execution is of interest
+        let accounts = match self {
+            ethers::types::PreStateFrame::Default(mode) => &mode.0,
+            ethers::types::PreStateFrame::Diff(diff) => &diff.pre,
+        };
+        PrestateTrace(
+            accounts
+                .iter()
+                .map(|(address, account)| (address.to_zkevm_type(), account.to_zkevm_type()))
+                .collect(),
+        )
+    }
+}
+
+impl TryConversion<TraceFrame> for anvil_types::GethTrace {
+    fn try_to_zkevm_type(&self) -> Result<TraceFrame, Error> {
+        Ok(match self {
+            ethers::types::GethTrace::Known(frame) => match frame {
+                ethers::types::GethTraceFrame::Default(frame) => {
+                    TraceFrame::StructLogs(frame.try_to_zkevm_type()?)
+                }
+                ethers::types::GethTraceFrame::CallTracer(frame) => {
+                    TraceFrame::Call(frame.to_zkevm_type())
+                }
+                ethers::types::GethTraceFrame::PreStateTracer(frame) => {
+                    TraceFrame::Prestate(frame.to_zkevm_type())
+                }
+                other => TraceFrame::Other(serde_json::to_value(other).unwrap_or_default()),
+            },
+            ethers::types::GethTrace::Unknown(value) => TraceFrame::Other(value.clone()),
+        })
+    }
+}
+
 impl Conversion<zkevm_types::EIP1186ProofResponse> for anvil_types::EIP1186ProofResponse {
     fn to_zkevm_type(&self) -> zkevm_types::EIP1186ProofResponse {
         zkevm_types::EIP1186ProofResponse {
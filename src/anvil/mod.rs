@@ -1,3 +1,9 @@
+#[cfg(feature = "anvil")]
 pub mod client;
+#[cfg(feature = "anvil")]
+pub mod config;
 pub mod conversion;
-pub use client::AnvilClient;
+#[cfg(feature = "anvil")]
+pub use client::{AnvilClient, WaitOptions};
+#[cfg(feature = "anvil")]
+pub use config::{AnvilClientConfig, MiningMode};
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use super::ChainBackend;
+use crate::{
+    error::Error,
+    trace::TraceFrame,
+    types::{anvil_types, zkevm_types},
+};
+
+/// Serves chain data from memory, for tests
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    pub chain_id: zkevm_types::Word,
+    pub blocks: HashMap<usize, zkevm_types::EthBlockFull>,
+    pub traces: HashMap<zkevm_types::Hash, zkevm_types::GethExecTrace>,
+    pub proofs: HashMap<(zkevm_types::Address, usize), zkevm_types::EIP1186ProofResponse>,
+    pub codes: HashMap<(zkevm_types::Address, usize), zkevm_types::Bytes>,
+}
+
+impl MemoryBackend {
+    pub fn new(chain_id: zkevm_types::Word) -> Self {
+        Self {
+            chain_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn insert_block(&mut self, block: zkevm_types::EthBlockFull) -> Result<(), Error> {
+        let block_number = block
+            .number
+            .ok_or_else(|| Error::rpc("block without number"))?;
+        self.blocks.insert(block_number.as_usize(), block);
+        Ok(())
+    }
+
+    fn latest_block_number(&self) -> usize {
+        self.blocks.keys().max().copied().unwrap_or_default()
+    }
+}
+
+fn to_header(block: &zkevm_types::EthBlockFull) -> zkevm_types::EthBlockHeader {
+    zkevm_types::Block {
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        uncles_hash: block.uncles_hash,
+        author: block.author,
+        state_root: block.state_root,
+        transactions_root: block.transactions_root,
+        receipts_root: block.receipts_root,
+        number: block.number,
+        gas_used: block.gas_used,
+        gas_limit: block.gas_limit,
+        extra_data: block.extra_data.clone(),
+        logs_bloom: block.logs_bloom,
+        timestamp: block.timestamp,
+        difficulty: block.difficulty,
+        total_difficulty: block.total_difficulty,
+        seal_fields: block.seal_fields.clone(),
+        uncles: block.uncles.clone(),
+        transactions: block.transactions.iter().map(|tx| tx.hash).collect(),
+        size: block.size,
+        mix_hash: block.mix_hash,
+        nonce: block.nonce,
+        base_fee_per_gas: block.base_fee_per_gas,
+        other: block.other.clone(),
+        withdrawals_root: block.withdrawals_root,
+        withdrawals: block.withdrawals.clone(),
+    }
+}
+
+#[async_trait]
+impl ChainBackend for MemoryBackend {
+    async fn chain_id(&self) -> Result<zkevm_types::Word, Error> {
+        Ok(self.chain_id)
+    }
+
+    async fn block_by_number(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        Ok(self.blocks.get(&block_number).map(to_header))
+    }
+
+    async fn block_by_number_full(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockFull>, Error> {
+        Ok(self.blocks.get(&block_number).cloned())
+    }
+
+    async fn block_by_hash(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        Ok(self
+            .blocks
+            .values()
+            .find(|block| block.hash == Some(hash))
+            .map(to_header))
+    }
+
+    async fn debug_trace_transaction_frame(
+        &self,
+        hash: zkevm_types::Hash,
+        _options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<TraceFrame, Error> {
+        self.traces
+            .get(&hash)
+            .cloned()
+            .map(TraceFrame::StructLogs)
+            .ok_or_else(|| Error::rpc("trace not found").with_tx(hash))
+    }
+
+    async fn get_proof(
+        &self,
+        address: zkevm_types::Address,
+        keys: Vec<zkevm_types::U256>,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::EIP1186ProofResponse, Error> {
+        let block_number = block_number.unwrap_or_else(|| self.latest_block_number());
+        let mut proof = self
+            .proofs
+            .get(&(address, block_number))
+            .cloned()
+            .ok_or_else(|| {
+                Error::rpc("proof not found")
+                    .with_block(block_number)
+                    .with_address(address)
+            })?;
+        proof
+            .storage_proof
+            .retain(|storage_proof| keys.contains(&zkevm_types::h256_to_u256(storage_proof.key)));
+        Ok(proof)
+    }
+
+    async fn get_code(
+        &self,
+        address: zkevm_types::Address,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::Bytes, Error> {
+        let block_number = block_number.unwrap_or_else(|| self.latest_block_number());
+        Ok(self
+            .codes
+            .get(&(address, block_number))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
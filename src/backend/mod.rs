@@ -0,0 +1,171 @@
+pub mod memory;
+pub mod rpc;
+
+use async_trait::async_trait;
+
+pub use memory::MemoryBackend;
+pub use rpc::RpcBackend;
+
+#[cfg(feature = "anvil")]
+use crate::anvil::AnvilClient;
+use crate::{
+    anvil::conversion::patch_trace,
+    error::Error,
+    trace::{CallTrace, PrestateTrace, TraceFrame},
+    types::{anvil_types, zkevm_types},
+};
+
+/// Backend of a `BuilderClient` when none is named, the embedded anvil fork
+/// if it is enabled
+#[cfg(feature = "anvil")]
+pub type DefaultBackend = AnvilClient;
+#[cfg(not(feature = "anvil"))]
+pub type DefaultBackend = RpcBackend;
+
+/// Chain data needed by the circuit input builder
+#[async_trait]
+pub trait ChainBackend: Send + Sync {
+    async fn chain_id(&self) -> Result<zkevm_types::Word, Error>;
+
+    async fn block_by_number(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error>;
+
+    async fn block_by_number_full(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockFull>, Error>;
+
+    async fn block_by_hash(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error>;
+
+    async fn debug_trace_transaction_frame(
+        &self,
+        hash: zkevm_types::Hash,
+        options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<TraceFrame, Error>;
+
+    async fn get_proof(
+        &self,
+        address: zkevm_types::Address,
+        keys: Vec<zkevm_types::U256>,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::EIP1186ProofResponse, Error>;
+
+    async fn get_code(
+        &self,
+        address: zkevm_types::Address,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::Bytes, Error>;
+
+    /// JSON-RPC endpoint serving the backend's own chain, used to prove
+    /// storage when there is no remote rpc
+    fn rpc_url(&self) -> Option<String> {
+        None
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        hash: zkevm_types::Hash,
+        options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<zkevm_types::GethExecTrace, Error> {
+        match self.debug_trace_transaction_frame(hash, options).await? {
+            TraceFrame::StructLogs(trace) => Ok(patch_trace(trace)),
+            _ => Err(Error::rpc("expected struct log trace").with_tx(hash)),
+        }
+    }
+
+    async fn debug_trace_transaction_calls(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<CallTrace, Error> {
+        let options = anvil_types::GethDebugTracingOptions {
+            tracer: Some(anvil_types::GethDebugTracerType::BuiltInTracer(
+                anvil_types::GethDebugBuiltInTracerType::CallTracer,
+            )),
+            ..Default::default()
+        };
+        match self.debug_trace_transaction_frame(hash, options).await? {
+            TraceFrame::Call(trace) => Ok(trace),
+            _ => Err(Error::rpc("expected callTracer output").with_tx(hash)),
+        }
+    }
+
+    async fn debug_trace_transaction_prestate(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<PrestateTrace, Error> {
+        let options = anvil_types::GethDebugTracingOptions {
+            tracer: Some(anvil_types::GethDebugTracerType::BuiltInTracer(
+                anvil_types::GethDebugBuiltInTracerType::PreStateTracer,
+            )),
+            ..Default::default()
+        };
+        match self.debug_trace_transaction_frame(hash, options).await? {
+            TraceFrame::Prestate(trace) => Ok(trace),
+            _ => Err(Error::rpc("expected prestateTracer output").with_tx(hash)),
+        }
+    }
+}
+
+#[cfg(feature = "anvil")]
+#[async_trait]
+impl ChainBackend for AnvilClient {
+    async fn chain_id(&self) -> Result<zkevm_types::Word, Error> {
+        self.eth_chain_id()?
+            .ok_or_else(|| Error::rpc("unable to get chain id from eth client"))
+    }
+
+    async fn block_by_number(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        AnvilClient::block_by_number(self, block_number).await
+    }
+
+    async fn block_by_number_full(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockFull>, Error> {
+        AnvilClient::block_by_number_full(self, block_number).await
+    }
+
+    async fn block_by_hash(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        AnvilClient::block_by_hash(self, hash).await
+    }
+
+    async fn debug_trace_transaction_frame(
+        &self,
+        hash: zkevm_types::Hash,
+        options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<TraceFrame, Error> {
+        AnvilClient::debug_trace_transaction_frame(self, hash, options).await
+    }
+
+    async fn get_proof(
+        &self,
+        address: zkevm_types::Address,
+        keys: Vec<zkevm_types::U256>,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::EIP1186ProofResponse, Error> {
+        AnvilClient::get_proof(self, address, keys, block_number).await
+    }
+
+    async fn get_code(
+        &self,
+        address: zkevm_types::Address,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::Bytes, Error> {
+        AnvilClient::get_code(self, address, block_number).await
+    }
+
+    fn rpc_url(&self) -> Option<String> {
+        Some(self.http_endpoint())
+    }
+}
@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use ethers::providers::{Http, Middleware, Provider};
+
+use super::ChainBackend;
+use crate::{
+    anvil::conversion::{patch_transaction, Conversion, ConversionReverse, TryConversion},
+    error::Error,
+    trace::TraceFrame,
+    types::{anvil_types, zkevm_types},
+};
+
+/// Reads chain data from any JSON-RPC node, e.g. an existing archive node
+pub struct RpcBackend {
+    provider: Provider<Http>,
+}
+
+impl RpcBackend {
+    pub fn new(provider: Provider<Http>) -> Self {
+        Self { provider }
+    }
+
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let provider = Provider::<Http>::try_from(url)
+            .map_err(|err| Error::config(format!("invalid rpc url {url}: {err}")))?;
+        Ok(Self::new(provider))
+    }
+}
+
+fn block_id(block_number: usize) -> anvil_types::BlockId {
+    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(anvil_types::U64::from(
+        block_number,
+    )))
+}
+
+#[async_trait]
+impl ChainBackend for RpcBackend {
+    async fn chain_id(&self) -> Result<zkevm_types::Word, Error> {
+        Ok(self.provider.get_chainid().await?.to_zkevm_type())
+    }
+
+    async fn block_by_number(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        Ok(self
+            .provider
+            .get_block(block_id(block_number))
+            .await?
+            .map(|block| block.to_zkevm_type()))
+    }
+
+    async fn block_by_number_full(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<zkevm_types::EthBlockFull>, Error> {
+        Ok(self
+            .provider
+            .get_block_with_txs(block_id(block_number))
+            .await?
+            .map(|block| {
+                let mut block: zkevm_types::EthBlockFull = block.to_zkevm_type();
+                block.transactions = block
+                    .transactions
+                    .into_iter()
+                    .map(patch_transaction)
+                    .collect();
+                block
+            }))
+    }
+
+    async fn block_by_hash(
+        &self,
+        hash: zkevm_types::Hash,
+    ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        Ok(self
+            .provider
+            .get_block(anvil_types::BlockId::Hash(hash.to_anvil_type()))
+            .await?
+            .map(|block| block.to_zkevm_type()))
+    }
+
+    async fn debug_trace_transaction_frame(
+        &self,
+        hash: zkevm_types::Hash,
+        options: anvil_types::GethDebugTracingOptions,
+    ) -> Result<TraceFrame, Error> {
+        Ok(self
+            .provider
+            .debug_trace_transaction(hash.to_anvil_type(), options)
+            .await?
+            .try_to_zkevm_type()?)
+    }
+
+    async fn get_proof(
+        &self,
+        address: zkevm_types::Address,
+        keys: Vec<zkevm_types::U256>,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::EIP1186ProofResponse, Error> {
+        Ok(self
+            .provider
+            .get_proof(
+                address.to_anvil_type(),
+                keys.iter().map(|key| key.to_anvil_type()).collect(),
+                block_number.map(block_id),
+            )
+            .await?
+            .to_zkevm_type())
+    }
+
+    async fn get_code(
+        &self,
+        address: zkevm_types::Address,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::Bytes, Error> {
+        Ok(self
+            .provider
+            .get_code(address.to_anvil_type(), block_number.map(block_id))
+            .await?
+            .to_zkevm_type())
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs::File,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    abi,
+    call::{CallRequest, ProvingSession},
+    config::BlockTag,
+    error::Error,
+    overrides::StateOverride,
+    prover::ProofBundle,
+    revert::OnRevert,
+    types::zkevm_types::{Address, Bytes, Word, H256},
+};
+
+/// One line of a batch file. Same fields as a `CallRequest`, the calldata
+/// can also be given as `sig` and `args`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchCall {
+    #[serde(default)]
+    pub from: Option<Address>,
+    /// missing for a contract creation
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default, alias = "input")]
+    pub data: Option<Bytes>,
+    #[serde(default)]
+    pub value: Option<Word>,
+    #[serde(default)]
+    pub gas: Option<Word>,
+    #[serde(default)]
+    pub block: Option<BlockTag>,
+    #[serde(default)]
+    pub overrides: StateOverride,
+    #[serde(default)]
+    pub sig: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub on_revert: OnRevert,
+}
+
+impl BatchCall {
+    pub fn into_request(self) -> Result<CallRequest, Error> {
+        self.overrides.validate()?;
+        if self.to.is_none() && self.sig.is_some() {
+            return Err(Error::config(
+                "sig needs a to address, pass the init code as data for a contract creation",
+            ));
+        }
+        let data = match (&self.sig, self.data) {
+            (Some(_), Some(_)) => return Err(Error::config("pass either data or sig, not both")),
+            (Some(sig), None) => abi::encode_call(&abi::parse_signature(sig)?, &self.args)?,
+            (None, Some(data)) => data,
+            (None, None) => return Err(Error::config("missing data or sig")),
+        };
+        let request = match self.to {
+            Some(to) => CallRequest::new(to, data),
+            None => CallRequest::create(data),
+        };
+        Ok(request
+            .with_from(self.from)
+            .with_value(self.value)
+            .with_gas(self.gas)
+            .with_block(self.block)
+            .with_overrides(self.overrides)
+            .with_sig(self.sig)
+            .with_on_revert(self.on_revert))
+    }
+}
+
+/// Outcome of one line of a batch file
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    /// 1-based line number in the batch file
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_data: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: f64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchResult>,
+}
+
+impl BatchSummary {
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
+
+/// Proves every call of a JSONL batch on the same session, writing a
+/// bundle per call to `out_dir`, see `ProofBundle::write`. A failing call is
+/// recorded in the summary and does not stop the batch, only reading the
+/// batch itself does.
+pub async fn prove_batch(
+    session: &mut ProvingSession,
+    batch: impl BufRead,
+    out_dir: &Path,
+) -> Result<BatchSummary, Error> {
+    let mut summary = BatchSummary::default();
+    for (index, line) in batch.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let start = Instant::now();
+        let result = prove_line(session, &line).await.and_then(|bundle| {
+            let path = bundle.write(out_dir)?;
+            Ok((bundle, path))
+        });
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        summary.results.push(match result {
+            Ok((bundle, path)) => {
+                info!(line = index + 1, path = %path.display(), "proved batch call");
+                summary.succeeded += 1;
+                BatchResult {
+                    line: index + 1,
+                    tx_hash: Some(bundle.tx_hash),
+                    return_data: Some(bundle.return_data),
+                    bundle: Some(path),
+                    error: None,
+                    duration_ms,
+                }
+            }
+            Err(err) => {
+                warn!(line = index + 1, %err, "batch call failed");
+                summary.failed += 1;
+                BatchResult {
+                    line: index + 1,
+                    tx_hash: None,
+                    return_data: None,
+                    bundle: None,
+                    error: Some(err.to_string()),
+                    duration_ms,
+                }
+            }
+        });
+    }
+    Ok(summary)
+}
+
+async fn prove_line(session: &mut ProvingSession, line: &str) -> Result<ProofBundle, Error> {
+    let request = serde_json::from_str::<BatchCall>(line)?.into_request()?;
+    session.prove(&request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchCall;
+    use crate::{config::BlockTag, types::zkevm_types::Address};
+
+    #[test]
+    fn test_batch_call() {
+        let call: BatchCall = serde_json::from_str(
+            r#"{"to":"0x35c6ace6404d8fd1cee19026b3d56d0c9627a646","sig":"balanceOf(address)(uint256)","args":["0x0000000000000000000000000000000000000001"],"block":"0x429588"}"#,
+        )
+        .unwrap();
+        let request = call.into_request().unwrap();
+        assert_eq!(request.data.len(), 4 + 32);
+        assert_eq!(request.block, Some(BlockTag::Number(4363656)));
+        assert_eq!(request.from, None::<Address>);
+
+        let call: BatchCall =
+            serde_json::from_str(r#"{"to":"0x35c6ace6404d8fd1cee19026b3d56d0c9627a646"}"#).unwrap();
+        assert!(call.into_request().is_err());
+
+        // without to the data is init code
+        let call: BatchCall =
+            serde_json::from_str(r#"{"data":"0x600a600c600039600a6000f3"}"#).unwrap();
+        let request = call.into_request().unwrap();
+        assert_eq!(request.to, None);
+        assert_eq!(request.data.len(), 12);
+        let call: BatchCall = serde_json::from_str(r#"{"sig":"name()(string)"}"#).unwrap();
+        assert!(call.into_request().is_err());
+        // unknown fields are rejected rather than silently ignored
+        assert!(serde_json::from_str::<BatchCall>(
+            r#"{"to":"0x35c6ace6404d8fd1cee19026b3d56d0c9627a646","calldata":"0x"}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use std::path::PathBuf;
+use tracing::info;
+use zk_eth_call::{
+    config::{Config, KeyFormat, ProverSettings, DEFAULT_K},
+    env,
+    error::Error,
+    prover::RealProver,
+    telemetry::Telemetry,
+};
+
+/// Rewrites cached params and keys into another format, by default into
+/// raw_bytes_unchecked which loads fastest. Pass the same format with
+/// --key-format or in the config file when proving afterwards.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    #[arg(long, help = "Directory with the params and keys [default: .]")]
+    dir: Option<PathBuf>,
+    #[arg(long, default_value_t = DEFAULT_K, help = "Circuit degree of the keys")]
+    k: u32,
+    #[arg(long, help = "Current format of the files [default: raw_bytes]")]
+    from: Option<String>,
+    #[arg(
+        long,
+        default_value = "raw_bytes_unchecked",
+        help = "Format to convert the files into"
+    )]
+    to: String,
+    #[arg(long, help = "TOML config file, see config.rs [default: $CONFIG]")]
+    config: Option<PathBuf>,
+}
+
+fn main() {
+    if let Err(err) = run(Args::parse()) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Error> {
+    Telemetry::init(false);
+
+    let env = env::Env::load()?;
+    let config = Config::load(args.config.as_deref().or(env.config_path.as_deref()))?;
+    let prover_settings = ProverSettings {
+        dir: args.dir,
+        serde_format: args
+            .from
+            .map(|format| format.parse::<KeyFormat>())
+            .transpose()?,
+    }
+    .or(config.prover.clone());
+    let to = args.to.parse::<KeyFormat>()?;
+
+    let prover = RealProver::init(args.k, prover_settings.dir())
+        .with_serde_format(prover_settings.serde_format());
+    let converted = prover.convert_files(to.into())?;
+    if converted.is_empty() {
+        return Err(Error::config(format!(
+            "no params or keys for k = {} found",
+            args.k
+        )));
+    }
+    for path in converted {
+        info!(path = %path.display(), format = ?to, "converted");
+    }
+    Ok(())
+}
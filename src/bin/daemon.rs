@@ -0,0 +1,120 @@
+use clap::Parser;
+use std::{fs::create_dir_all, net::SocketAddr, path::PathBuf, sync::Arc};
+use tracing::info;
+use zk_eth_call::{
+    anvil::AnvilClientConfig,
+    call::{ProverOptions, ProvingSession},
+    config::{BlockTag, Config, KeyFormat, NetworkProfile, ProverSettings},
+    daemon::{router, Daemon},
+    env,
+    error::Error,
+    jobs::JobStore,
+    telemetry::Telemetry,
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    #[arg(long, help = "Archive node for mainnet fork [required]")]
+    rpc: Option<String>,
+    #[arg(long, help = "Block number for mainnet fork [default: latest]")]
+    block: Option<usize>,
+    #[arg(long, default_value_t = 8546, help = "Port to serve the HTTP API on")]
+    port: u16,
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Number of jobs proven at the same time"
+    )]
+    max_concurrent: usize,
+    #[arg(
+        long,
+        help = "File the job state is kept in [default: <dir>/jobs.json]"
+    )]
+    jobs: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Use MockProver for fast constraint verification [default: false]"
+    )]
+    mock: bool,
+    #[arg(
+        long,
+        help = "Directory for reading and writing proving keys [default: .]"
+    )]
+    dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Format of params and keys: processed, raw_bytes or raw_bytes_unchecked [default: raw_bytes]"
+    )]
+    key_format: Option<String>,
+    #[arg(long, help = "TOML config file, see config.rs [default: $CONFIG]")]
+    config: Option<PathBuf>,
+    #[arg(long, help = "Network profile from the config file [default: default]")]
+    network: Option<String>,
+    #[arg(long, help = "Circuit profile from the config file [default: default]")]
+    circuit: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(Args::parse()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    Telemetry::init(false);
+
+    let env = env::Env::load()?;
+    let config = Config::load(args.config.as_deref().or(env.config_path.as_deref()))?;
+    let network = NetworkProfile {
+        rpc: args.rpc,
+        chain_id: None,
+        block: args.block.map(BlockTag::Number),
+    }
+    .or(env.network())
+    .or(config.network(args.network.as_deref())?);
+    let circuit = config.circuit(args.circuit.as_deref())?;
+    let prover_settings = ProverSettings {
+        dir: args.dir,
+        serde_format: args
+            .key_format
+            .map(|format| format.parse::<KeyFormat>())
+            .transpose()?,
+    }
+    .or(config.prover.clone());
+    if network.rpc.is_none() {
+        return Err(Error::config(
+            "no rpc url, pass --rpc, set ETH_RPC_URL or add it to a network profile",
+        ));
+    }
+
+    let dir_path = prover_settings.dir();
+    create_dir_all(&dir_path)?;
+    let session = ProvingSession::new(
+        AnvilClientConfig::default()
+            .with_eth_rpc_url(network.rpc.clone())
+            .with_fork_block_number(network.fork_block_number()),
+        ProverOptions::default()
+            .with_k(circuit.k()?)
+            .with_circuits_params(circuit.circuits_params()?)
+            .with_mock(args.mock)
+            .with_dir(dir_path.clone())
+            .with_serde_format(prover_settings.serde_format()),
+    )
+    .await?;
+    network.check_chain_id(session.builder.chain_id)?;
+    session.load_prover().await?;
+    let jobs = JobStore::open(args.jobs.unwrap_or_else(|| dir_path.join("jobs.json")))?;
+    let daemon = Arc::new(Daemon::new(session, jobs, args.max_concurrent));
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], args.port));
+    info!(%addr, max_concurrent = args.max_concurrent, "listening");
+    axum::Server::try_bind(&addr)
+        .map_err(|err| Error::config(format!("cannot listen on {addr}: {err}")))?
+        .serve(router(daemon).into_make_service())
+        .await
+        .map_err(|err| Error::rpc(format!("server failed: {err}")))
+}
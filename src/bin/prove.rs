@@ -1,57 +1,102 @@
-use bus_mapping::circuit_input_builder::CircuitsParams;
-use clap::Parser;
-use eth_types::Fr;
-use ethers::{
-    abi::Address,
-    signers::{LocalWallet, Signer},
-    types::{transaction::eip2718::TypedTransaction, Bytes, TransactionRequest},
-    utils::parse_units,
-};
+use anvil::Hardfork;
+use clap::{Parser, Subcommand};
+use ethers::signers::LocalWallet;
 use ethers_core::utils::hex;
-use halo2_proofs::{
-    dev::MockProver,
-    halo2curves::bn256::{Bn256, G1Affine},
-    plonk::{create_proof, keygen_pk, keygen_vk, ProvingKey, VerifyingKey},
-    poly::{
-        commitment::ParamsProver,
-        kzg::{
-            commitment::{KZGCommitmentScheme, ParamsKZG},
-            multiopen::ProverSHPLONK,
-        },
-    },
-    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
-    SerdeFormat,
-};
-use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng, ChaChaRng};
 use std::{
-    fs::{create_dir_all, File},
-    io::Write,
+    fs::{create_dir_all, write, File},
+    io::{BufReader, Write},
     path::{Path, PathBuf},
     str::FromStr,
 };
-use zk_eth_call::{anvil::conversion::Conversion, env, error::Error, BuilderClient};
-use zkevm_circuits::{super_circuit::SuperCircuit, util::SubCircuit};
-
-const MAX_TXS: usize = 1;
-const MAX_CALLDATA: usize = 256;
-const RANDOMNESS: u64 = 0x100;
+use tracing::info;
+use zk_eth_call::{
+    abi,
+    anvil::AnvilClientConfig,
+    batch::prove_batch,
+    call::{prove_witness_file, CallRequest, ProverOptions, ProvingSession},
+    config::{BlockTag, CircuitProfile, Config, KeyFormat, NetworkProfile, ProverSettings},
+    env,
+    error::Error,
+    overrides::StateOverride,
+    prover::ProofBundle,
+    revert::OnRevert,
+    telemetry::Telemetry,
+    types::zkevm_types::{Address, Bytes, Word},
+    witness::WitnessFile,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
-    // required args
+    // required args, unless set in the environment or the config file
     #[arg(long, help = "Archive node for mainnet fork [required]")]
-    rpc: String,
-    #[arg(long, help = "Block number for mainnet fork [required]")]
-    block: usize,
+    rpc: Option<String>,
+    #[arg(long, help = "Block number for mainnet fork [default: latest]")]
+    block: Option<usize>,
     // #[arg(long, help = "Witness tx, which should solve the challenge [required]")]
     // raw_tx: String,
-    #[arg(long, help = "destination [required]")]
-    to: String,
-    #[arg(long, help = "calldata [required]")]
-    calldata: String,
+    #[arg(
+        long,
+        help = "destination, calldata is the init code without it [default: contract creation]"
+    )]
+    to: Option<Address>,
+    #[arg(
+        long,
+        required_unless_present_any = ["sig", "batch", "witness"],
+        conflicts_with = "sig",
+        help = "calldata [required unless --sig, --batch or --witness]"
+    )]
+    calldata: Option<Bytes>,
+    #[arg(
+        long,
+        requires = "to",
+        help = "Function signature with return types, e.g. \"balanceOf(address)(uint256)\""
+    )]
+    sig: Option<String>,
+    #[arg(long, num_args = 0.., requires = "sig", help = "Arguments for --sig")]
+    args: Vec<String>,
+    #[arg(
+        long,
+        conflicts_with_all = ["to", "calldata", "sig", "overrides", "value", "gas", "on_revert"],
+        help = "JSONL file with one call per line, same fields as a zk_call call object plus block, overrides, sig and args"
+    )]
+    batch: Option<PathBuf>,
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "to", "calldata", "sig", "batch", "overrides", "value", "gas", "on_revert", "trace_call"
+        ],
+        help = "Prove a witness file written by the witness subcommand, no rpc needed"
+    )]
+    witness: Option<PathBuf>,
 
     // optional args
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Prove from debug_traceCall without signing or mining a transaction [default: false]"
+    )]
+    trace_call: bool,
+    #[arg(
+        long,
+        help = "Caller, has to be the address of the signing key: SK, or the first anvil dev account with --trace-call [default: that address]"
+    )]
+    from: Option<Address>,
+    #[arg(
+        long,
+        help = "What to do if the call reverts: refuse to prove it or allow it, the revert itself is not attested [default: refuse]"
+    )]
+    on_revert: Option<String>,
+    #[arg(
+        long,
+        help = "Wei sent with the call, or with a unit, e.g. 1.5ether [default: 0]"
+    )]
+    value: Option<String>,
+    #[arg(
+        long,
+        help = "Gas limit of the call [default: eth_estimateGas plus 20%]"
+    )]
+    gas: Option<u64>,
     #[arg(
         long,
         default_value_t = false,
@@ -61,323 +106,276 @@ struct Args {
     #[arg(
         long,
         default_value_t = false,
-        help = "Print witness and public inputs that has been provided to zkevm circuits [default: false]"
+        help = "Write the block witness provided to the zkevm circuits to block_witness_<hash>.txt in --dir [default: false]"
     )]
     print: bool,
+    #[arg(long, help = "Directory for reading and writing [default: .]")]
+    dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Format of params and keys: processed, raw_bytes or raw_bytes_unchecked [default: raw_bytes]"
+    )]
+    key_format: Option<String>,
+    #[arg(long, help = "TOML config file, see config.rs [default: $CONFIG]")]
+    config: Option<PathBuf>,
+    #[arg(long, help = "Network profile from the config file [default: default]")]
+    network: Option<String>,
+    #[arg(long, help = "Circuit profile from the config file [default: default]")]
+    circuit: Option<String>,
+    #[arg(long, help = "Circuit degree [default: 19]")]
+    k: Option<u32>,
+    #[arg(
+        long,
+        help = "JSON file with an eth_call style state override set, assumed and not proven"
+    )]
+    overrides: Option<String>,
     #[arg(
         long,
-        default_value_t = String::new(),
-        help = "Directory for reading and writing [default: false]"
+        help = "Hardfork to run the fork with, e.g. shanghai [default: latest]"
     )]
-    dir: String,
-    #[arg(long, default_value_t = 1000)]
-    max_rws: usize,
-    #[arg(long, default_value_t = 1000)]
-    max_copy_rows: usize,
-    #[arg(long, default_value_t = 1000)]
-    max_exp_steps: usize,
-    #[arg(long, default_value_t = 512)]
-    max_bytecode: usize,
-    #[arg(long, default_value_t = 1000)]
-    max_evm_rows: usize,
-    #[arg(long, default_value_t = 10000)]
-    max_keccak_rows: usize,
+    hardfork: Option<String>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Log as JSON lines, filtered with RUST_LOG [default: false]"
+    )]
+    log_json: bool,
+    #[arg(
+        long,
+        help = "JSON file for the per phase timing and memory summary [default: <dir>/timings.json]"
+    )]
+    timings: Option<PathBuf>,
+    #[arg(long, help = "[default: 1000]")]
+    max_rws: Option<usize>,
+    #[arg(long, help = "[default: 1000]")]
+    max_copy_rows: Option<usize>,
+    #[arg(long, help = "[default: 1000]")]
+    max_exp_steps: Option<usize>,
+    #[arg(long, help = "[default: 512]")]
+    max_bytecode: Option<usize>,
+    #[arg(long, help = "[default: 1000]")]
+    max_evm_rows: Option<usize>,
+    #[arg(long, help = "[default: 10000]")]
+    max_keccak_rows: Option<usize>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Only generate the witness and write it to a file, to be proven with
+    /// --witness, e.g. on another machine
+    Witness {
+        #[arg(long, help = "File to write the witness to")]
+        out: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
-
-    let builder = BuilderClient::from_config(
-        CircuitsParams {
-            max_rws: args.max_rws,
-            max_txs: MAX_TXS,
-            max_calldata: MAX_CALLDATA,
-            max_copy_rows: args.max_copy_rows,
-            max_exp_steps: args.max_exp_steps,
-            max_bytecode: args.max_bytecode,
-            max_evm_rows: args.max_evm_rows,
-            max_keccak_rows: args.max_keccak_rows,
-        },
-        Some(args.rpc),
-        Some(args.block),
-    )
-    .await
-    .unwrap();
-
-    let chain_id = builder.anvil.eth_chain_id().unwrap().unwrap();
-    let block_number = builder.anvil.block_number().unwrap();
-    println!("chain_id: {chain_id:?}, block_number: {block_number:?}");
-
-    println!("executing...");
-
-    let env = env::Env::load();
-    let wallet = env.signing_key.unwrap().parse::<LocalWallet>().unwrap();
-    let tx_req = TransactionRequest::new()
-        .to(args.to.parse::<Address>().unwrap())
-        .data(args.calldata.parse::<Bytes>().unwrap())
-        .nonce(
-            builder
-                .anvil
-                .get_nonce(wallet.address(), Some(block_number))
-                .await
-                .unwrap(),
-        )
-        .chain_id(chain_id.as_u64())
-        .gas_price(parse_units("10", 9).unwrap())
-        .gas(100_000);
-    let tx = TypedTransaction::Legacy(tx_req.clone());
-    let signature = wallet.sign_transaction(&tx).await.unwrap();
-    let tx = tx_req.rlp_signed(&signature);
-
-    let hash = builder
-        .anvil
-        .send_raw_transaction(tx.to_zkevm_type())
-        .await
-        .unwrap();
-
-    builder.anvil.wait_for_transaction(hash).await.unwrap();
-
-    let rc = builder
-        .anvil
-        .transaction_receipt(hash)
-        .await
-        .unwrap()
-        .unwrap();
-
-    // println!("tx confirmed on anvil, hash: {}", hex::encode(hash));
-
-    let tx = builder
-        .anvil
-        .transaction_by_hash(hash)
-        .await
-        .unwrap()
-        .unwrap();
-
-    // println!("generating witness");
-    let mut witness = builder
-        .gen_witness(tx.block_number.unwrap().as_usize())
-        .await
-        .unwrap();
-    witness.randomness = Fr::from(RANDOMNESS);
-    println!("witness generated");
+    if let Err(err) = run(Args::parse()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
 
-    let (_, _rows_needed) =
-        SuperCircuit::<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>::min_num_rows_block(&witness);
-    let circuit = SuperCircuit::<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>::new_from_block(&witness);
-    let k = 19; // log2_ceil(64 + rows_needed);
-    println!("k: {k}");
-    let instance = circuit.instance();
-    if args.print {
-        println!("block witness: {witness:#?}");
+async fn run(args: Args) -> Result<(), Error> {
+    if args.command.is_some() && (args.batch.is_some() || args.witness.is_some()) {
+        return Err(Error::config(
+            "the witness subcommand writes the witness of a single call, it cannot be combined with --batch or --witness",
+        ));
     }
-    println!("instance: {:#?}", instance[0]);
+    let telemetry = Telemetry::init(args.log_json);
 
-    if args.mock {
-        println!("running MockProver");
-        let prover = MockProver::run(k, &circuit, instance).unwrap();
-        println!("verifying constraints");
-        prover.assert_satisfied_par();
-        println!("success");
-    } else {
-        let mut dir_path = PathBuf::from_str(".").unwrap();
-        if !args.dir.is_empty() {
-            dir_path = dir_path.join(args.dir)
-        }
-        create_dir_all(dir_path.clone()).unwrap();
+    // cli flags take precedence over the environment, which takes precedence
+    // over the config file
+    let env = env::Env::load()?;
+    let config = Config::load(args.config.as_deref().or(env.config_path.as_deref()))?;
+    let network = NetworkProfile {
+        rpc: args.rpc,
+        chain_id: None,
+        block: args.block.map(BlockTag::Number),
+    }
+    .or(env.network())
+    .or(config.network(args.network.as_deref())?);
+    let circuit = CircuitProfile {
+        k: args.k,
+        max_rws: args.max_rws,
+        max_copy_rows: args.max_copy_rows,
+        max_exp_steps: args.max_exp_steps,
+        max_bytecode: args.max_bytecode,
+        max_evm_rows: args.max_evm_rows,
+        max_keccak_rows: args.max_keccak_rows,
+        ..Default::default()
+    }
+    .or(config.circuit(args.circuit.as_deref())?);
+    let prover_settings = ProverSettings {
+        dir: args.dir,
+        serde_format: args
+            .key_format
+            .map(|format| format.parse::<KeyFormat>())
+            .transpose()?,
+    }
+    .or(config.prover.clone());
 
-        println!("running RealProver");
-        let mut prover = RealProver::init(k, dir_path.clone());
-        prover.setup_global().unwrap();
-        prover.setup_circuit(circuit.clone()).unwrap();
+    let signer = match args.trace_call || args.witness.is_some() {
+        true => None,
+        false => Some(
+            env.signing_key
+                .ok_or_else(|| Error::config("no signing key, set SK or pass --trace-call"))?
+                .parse::<LocalWallet>()
+                .map_err(|_| Error::config("SK is not a valid private key"))?,
+        ),
+    };
+    let dir_path = prover_settings.dir();
+    create_dir_all(dir_path.clone())?;
+    let options = ProverOptions::default()
+        .with_k(circuit.k()?)
+        .with_circuits_params(circuit.circuits_params()?)
+        .with_mock(args.mock)
+        .with_dir(dir_path.clone())
+        .with_serde_format(prover_settings.serde_format())
+        .with_signer(signer);
+    let timings_path = args
+        .timings
+        .clone()
+        .unwrap_or_else(|| dir_path.join("timings.json"));
 
-        println!("generating proof");
-        let proof = prover.prove(circuit, instance).unwrap();
-        let proof_path = dir_path.join(Path::new(&format!(
-            "proof_{}_{}",
-            prover.degree,
-            hex::encode(hash)
-        )));
-        println!("writing proof to {}", proof_path.display());
-        let mut file = File::create(proof_path).unwrap();
-        file.write_all(proof.as_slice()).unwrap();
-        println!("success");
+    if let Some(witness_path) = &args.witness {
+        let witness_file = WitnessFile::read(witness_path)?;
+        network.check_chain_id(witness_file.inputs.chain_id)?;
+        let bundle = prove_witness_file(witness_file, &options)?;
+        write_bundle(&bundle, &dir_path)?;
+        return telemetry.write_summary(&timings_path);
     }
-}
+    if network.rpc.is_none() {
+        return Err(Error::config(
+            "no rpc url, pass --rpc, set ETH_RPC_URL or add it to a network profile",
+        ));
+    }
+    let hardfork = args
+        .hardfork
+        .map(|hardfork| {
+            Hardfork::from_str(&hardfork)
+                .map_err(|_| Error::config(format!("unknown hardfork {hardfork}")))
+        })
+        .transpose()?;
 
-struct RealProver {
-    degree: u32,
-    dir_path: PathBuf,
-    serde_format: SerdeFormat,
-    rng: Option<ChaCha20Rng>,
-    general_params: Option<ParamsKZG<Bn256>>,
-    verifier_params: Option<ParamsKZG<Bn256>>,
-    circuit_proving_key: Option<ProvingKey<G1Affine>>,
-    circuit_verifying_key: Option<VerifyingKey<G1Affine>>,
-}
+    let mut session = ProvingSession::new(
+        AnvilClientConfig::default()
+            .with_eth_rpc_url(network.rpc.clone())
+            .with_fork_block_number(network.fork_block_number())
+            .with_hardfork(hardfork),
+        options,
+    )
+    .await?;
+    network.check_chain_id(session.builder.chain_id)?;
+    info!(
+        chain_id = %session.builder.chain_id,
+        fork_block_number = ?session.builder.fork_block_number,
+        "forked"
+    );
 
-impl RealProver {
-    fn init(degree: u32, dir_path: PathBuf) -> Self {
-        Self {
-            degree,
-            dir_path,
-            serde_format: SerdeFormat::RawBytes,
-            rng: None,
-            general_params: None,
-            verifier_params: None,
-            circuit_proving_key: None,
-            circuit_verifying_key: None,
+    if let Some(batch) = &args.batch {
+        let file = BufReader::new(File::open(batch)?);
+        let summary = prove_batch(&mut session, file, &dir_path).await?;
+        let summary_path = dir_path.join("batch_summary.json");
+        summary.write(&summary_path)?;
+        info!(
+            succeeded = summary.succeeded,
+            failed = summary.failed,
+            path = %summary_path.display(),
+            "batch done"
+        );
+        telemetry.write_summary(&timings_path)?;
+        if summary.failed > 0 {
+            return Err(Error::proof(format!(
+                "{} calls of the batch failed, see {}",
+                summary.failed,
+                summary_path.display()
+            )));
         }
+        return Ok(());
     }
 
-    fn setup_global(&mut self) -> Result<(), Error> {
-        self.setup_general_params()?;
-        self.setup_verifier_params()?;
-        Ok(())
-    }
+    let calldata = match (&args.sig, args.calldata) {
+        (Some(sig), _) => abi::encode_call(&abi::parse_signature(sig)?, &args.args)?,
+        (None, Some(calldata)) => calldata,
+        (None, None) => return Err(Error::config("no calldata, pass --calldata or --sig")),
+    };
+    let overrides = match args.overrides {
+        Some(overrides) => StateOverride::from_json_file(Path::new(&overrides))?,
+        None => StateOverride::default(),
+    };
+    let request = match args.to {
+        Some(to) => CallRequest::new(to, calldata),
+        None => CallRequest::create(calldata),
+    };
+    let request = request
+        .with_from(args.from)
+        .with_value(
+            args.value
+                .map(|value| abi::parse_value(&value))
+                .transpose()?,
+        )
+        .with_gas(args.gas.map(Word::from))
+        .with_overrides(overrides)
+        .with_sig(args.sig)
+        .with_on_revert(
+            args.on_revert
+                .map(|on_revert| on_revert.parse::<OnRevert>())
+                .transpose()?
+                .unwrap_or_default(),
+        );
 
-    fn prove(
-        &mut self,
-        circuit: SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>,
-        instance: Vec<Vec<Fr>>,
-    ) -> Result<Vec<u8>, Error> {
-        let instance_refs: Vec<&[Fr]> = instance.iter().map(|v| &v[..]).collect();
-        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
-        create_proof::<
-            KZGCommitmentScheme<Bn256>,
-            ProverSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            ChaChaRng,
-            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            _,
-        >(
-            self.general_params.as_mut().unwrap(),
-            self.circuit_proving_key.as_mut().unwrap(),
-            &[circuit],
-            &[&instance_refs],
-            self.rng.to_owned().unwrap(),
-            &mut transcript,
+    let call_witness = session.witness(&request).await?;
+    if args.print {
+        let witness_path = dir_path.join(format!("block_witness_{:?}.txt", call_witness.tx.hash));
+        write(&witness_path, format!("{:#?}", call_witness.witness))?;
+        info!(path = %witness_path.display(), "wrote block witness");
+    }
+    if let Some(Command::Witness { out }) = &args.command {
+        WitnessFile::new(
+            &call_witness,
+            session.builder.state_override.clone(),
+            request.sig.clone(),
         )
-        .unwrap();
-        Ok(transcript.finalize())
+        .write(out)?;
+        info!(path = %out.display(), "wrote witness");
+        return telemetry.write_summary(&timings_path);
     }
+    let bundle = session.prove_witness(call_witness, request.sig.as_deref())?;
+    write_bundle(&bundle, &dir_path)?;
 
-    fn setup_general_params(&mut self) -> Result<(), Error> {
-        self.rng = Some(ChaChaRng::seed_from_u64(2));
-        let path = self
-            .dir_path
-            .join(Path::new(&format!("kzg_general_params_{}", self.degree)));
-        match File::open(path.clone()) {
-            Ok(mut file) => {
-                println!("reading {}", path.display());
-                self.general_params = Some(ParamsKZG::<Bn256>::read_custom(
-                    &mut file,
-                    self.serde_format,
-                )?);
-            }
-            Err(_) => {
-                println!("setting up general params");
-                let general_params =
-                    ParamsKZG::<Bn256>::setup(self.degree, self.rng.as_mut().unwrap());
-                println!("writing {}", path.display());
-                let mut file = File::create(path)?;
-                general_params.write_custom(&mut file, self.serde_format)?;
-                self.general_params = Some(general_params);
-            }
-        };
-        Ok(())
-    }
+    telemetry.write_summary(&timings_path)?;
+    info!(path = %timings_path.display(), "wrote timing summary");
+    Ok(())
+}
 
-    fn setup_verifier_params(&mut self) -> Result<(), Error> {
-        let path = self
-            .dir_path
-            .join(Path::new(&format!("kzg_verifier_params_{}", self.degree)));
-        match File::open(path.clone()) {
-            Ok(mut file) => {
-                println!("reading {}", path.display());
-                self.verifier_params = Some(ParamsKZG::<Bn256>::read_custom(
-                    &mut file,
-                    self.serde_format,
-                )?);
-            }
-            Err(_) => {
-                println!("setting up verifier params");
-                let general_params = self.general_params.clone().unwrap();
-                let verifier_params = general_params.verifier_params().to_owned();
-                println!("writing {}", path.display());
-                let mut file = File::create(path)?;
-                verifier_params.write_custom(&mut file, self.serde_format)?;
-                self.verifier_params = Some(verifier_params);
-            }
-        };
-        Ok(())
+/// Logs the outcome and writes the proof and bundle files if there is a proof
+fn write_bundle(bundle: &ProofBundle, dir_path: &Path) -> Result<(), Error> {
+    if let Some(decoded_return) = &bundle.decoded_return {
+        info!(%decoded_return, "decoded return");
+    }
+    if let Some(revert_reason) = &bundle.revert_reason {
+        info!(%revert_reason, "proving a reverted call");
     }
 
-    fn setup_circuit(
-        &mut self,
-        circuit: SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>,
-    ) -> Result<(), Error> {
-        let verifying_key_path = self
-            .dir_path
-            .join(Path::new(&format!("circuit_verifying_key_{}", self.degree)));
-        match File::open(verifying_key_path.clone()) {
-            Ok(mut file) => {
-                println!("reading {}", verifying_key_path.display());
-                self.circuit_verifying_key = Some(
-                    VerifyingKey::<G1Affine>::read::<
-                        File,
-                        SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>,
-                    >(
-                        &mut file,
-                        self.serde_format,
-                        // circuit.params(),
-                    )
-                    .unwrap(),
-                );
-            }
-            Err(_) => {
-                println!("setting up verifying key");
-                let vk = keygen_vk(self.general_params.as_mut().unwrap(), &circuit)
-                    .expect("keygen_vk should not fail");
-                println!("writing {}", verifying_key_path.display());
-                let mut file = File::create(verifying_key_path)?;
-                vk.write(&mut file, self.serde_format)?;
-                self.circuit_verifying_key = Some(vk);
-            }
-        };
+    match &bundle.proof {
+        None => info!("mock verification passed"),
+        Some(proof) => {
+            let proof_path = dir_path.join(Path::new(&format!(
+                "proof_{}_{}",
+                bundle.k,
+                hex::encode(bundle.tx_hash)
+            )));
+            info!(path = %proof_path.display(), "writing proof");
+            let mut file = File::create(proof_path)?;
+            file.write_all(proof)?;
 
-        let proving_key_path = self
-            .dir_path
-            .join(Path::new(&format!("circuit_proving_key_{}", self.degree)));
-        match File::open(proving_key_path.clone()) {
-            Ok(mut file) => {
-                println!("reading {}", proving_key_path.display());
-                self.circuit_proving_key = Some(
-                    ProvingKey::<G1Affine>::read::<
-                        File,
-                        SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>,
-                    >(
-                        &mut file,
-                        self.serde_format,
-                        // circuit.params(),
-                    )
-                    .unwrap(),
-                );
-            }
-            Err(_) => {
-                println!("setting up proving key");
-                let pk = keygen_pk(
-                    self.general_params.as_mut().unwrap(),
-                    self.circuit_verifying_key.clone().unwrap(),
-                    &circuit,
-                )
-                .expect("keygen_pk should not fail");
-                println!("writing {}", proving_key_path.display());
-                let mut file = File::create(proving_key_path)?;
-                pk.write(&mut file, self.serde_format)?;
-                self.circuit_proving_key = Some(pk);
-            }
-        };
-        Ok(())
+            let bundle_path = bundle.write(dir_path)?;
+            info!(path = %bundle_path.display(), "wrote proof bundle");
+        }
     }
+    Ok(())
 }
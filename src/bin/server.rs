@@ -0,0 +1,111 @@
+use axum::{extract::State, routing::post, Json, Router};
+use clap::Parser;
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use tracing::info;
+use zk_eth_call::{
+    anvil::AnvilClientConfig,
+    call::{ProverOptions, ProvingSession},
+    config::{BlockTag, Config, KeyFormat, NetworkProfile, ProverSettings},
+    env,
+    error::Error,
+    rpc::{RpcResponse, ZkCallService},
+    telemetry::Telemetry,
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    #[arg(long, help = "Archive node for mainnet fork [required]")]
+    rpc: Option<String>,
+    #[arg(long, help = "Block number for mainnet fork [default: latest]")]
+    block: Option<usize>,
+    #[arg(long, default_value_t = 8545, help = "Port to serve JSON-RPC on")]
+    port: u16,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Use MockProver for fast constraint verification [default: false]"
+    )]
+    mock: bool,
+    #[arg(
+        long,
+        help = "Directory for reading and writing proving keys [default: .]"
+    )]
+    dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Format of params and keys: processed, raw_bytes or raw_bytes_unchecked [default: raw_bytes]"
+    )]
+    key_format: Option<String>,
+    #[arg(long, help = "TOML config file, see config.rs [default: $CONFIG]")]
+    config: Option<PathBuf>,
+    #[arg(long, help = "Network profile from the config file [default: default]")]
+    network: Option<String>,
+    #[arg(long, help = "Circuit profile from the config file [default: default]")]
+    circuit: Option<String>,
+}
+
+async fn handler(State(service): State<Arc<ZkCallService>>, body: String) -> Json<RpcResponse> {
+    Json(service.handle_raw(&body).await)
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(Args::parse()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    Telemetry::init(false);
+
+    let env = env::Env::load()?;
+    let config = Config::load(args.config.as_deref().or(env.config_path.as_deref()))?;
+    let network = NetworkProfile {
+        rpc: args.rpc,
+        chain_id: None,
+        block: args.block.map(BlockTag::Number),
+    }
+    .or(env.network())
+    .or(config.network(args.network.as_deref())?);
+    let circuit = config.circuit(args.circuit.as_deref())?;
+    let prover_settings = ProverSettings {
+        dir: args.dir,
+        serde_format: args
+            .key_format
+            .map(|format| format.parse::<KeyFormat>())
+            .transpose()?,
+    }
+    .or(config.prover.clone());
+    if network.rpc.is_none() {
+        return Err(Error::config(
+            "no rpc url, pass --rpc, set ETH_RPC_URL or add it to a network profile",
+        ));
+    }
+
+    let session = ProvingSession::new(
+        AnvilClientConfig::default()
+            .with_eth_rpc_url(network.rpc.clone())
+            .with_fork_block_number(network.fork_block_number()),
+        ProverOptions::default()
+            .with_k(circuit.k()?)
+            .with_circuits_params(circuit.circuits_params()?)
+            .with_mock(args.mock)
+            .with_dir(prover_settings.dir())
+            .with_serde_format(prover_settings.serde_format()),
+    )
+    .await?;
+    network.check_chain_id(session.builder.chain_id)?;
+    session.load_prover().await?;
+    let service = Arc::new(ZkCallService::new(session));
+
+    let app = Router::new().route("/", post(handler)).with_state(service);
+    let addr = SocketAddr::from(([127, 0, 0, 1], args.port));
+    info!(%addr, "listening");
+    axum::Server::try_bind(&addr)
+        .map_err(|err| Error::config(format!("cannot listen on {addr}: {err}")))?
+        .serve(app.into_make_service())
+        .await
+        .map_err(|err| Error::rpc(format!("server failed: {err}")))
+}
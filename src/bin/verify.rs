@@ -0,0 +1,175 @@
+use clap::Parser;
+use std::{fs::File, io::BufReader, path::PathBuf};
+use zk_eth_call::{
+    abi,
+    backend::RpcBackend,
+    config::{Config, KeyFormat, NetworkProfile, ProverSettings},
+    env,
+    error::Error,
+    overrides::StateOverride,
+    prover::{ProofBundle, RealProver},
+    telemetry::Telemetry,
+    types::zkevm_types::{Address, Bytes, H256},
+    verifier::{expected_instance_from_backend, CallClaim},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Args {
+    #[arg(long, help = "Trusted node to read block headers from [required]")]
+    rpc: Option<String>,
+    #[arg(long, help = "Chain id the call is claimed on [required]")]
+    chain_id: Option<u64>,
+    #[arg(
+        long,
+        help = "Hash of the block the call is claimed to run on top of, the fork block [required]"
+    )]
+    block_hash: H256,
+    #[arg(
+        long,
+        required_unless_present = "create",
+        conflicts_with = "create",
+        help = "Destination [required unless --create]"
+    )]
+    to: Option<Address>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Claim a contract creation with the calldata as init code [default: false]"
+    )]
+    create: bool,
+    #[arg(long, help = "Caller [default: zero address]")]
+    from: Option<Address>,
+    #[arg(
+        long,
+        help = "Wei sent with the call, or with a unit, e.g. 1.5ether [default: 0]"
+    )]
+    value: Option<String>,
+    #[arg(
+        long,
+        required_unless_present = "sig",
+        conflicts_with = "sig",
+        help = "calldata [required unless --sig]"
+    )]
+    calldata: Option<Bytes>,
+    #[arg(
+        long,
+        requires = "to",
+        help = "Function signature, e.g. \"balanceOf(address)(uint256)\""
+    )]
+    sig: Option<String>,
+    #[arg(long, num_args = 0.., requires = "sig", help = "Arguments for --sig")]
+    args: Vec<String>,
+    #[arg(
+        long,
+        help = "Claimed return data, the revert data with --reverted [required]"
+    )]
+    return_data: Bytes,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Claim that the call reverted [default: false]"
+    )]
+    reverted: bool,
+    #[arg(
+        long,
+        help = "State override JSON file with the state the call is claimed to assume, same format as for prove [default: none]"
+    )]
+    overrides: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Bundle written by prove, with the proof and its instance [required]"
+    )]
+    bundle: PathBuf,
+    #[arg(
+        long,
+        help = "Directory with the verifier params and verifying key [default: .]"
+    )]
+    dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Format of params and keys: processed, raw_bytes or raw_bytes_unchecked [default: raw_bytes]"
+    )]
+    key_format: Option<String>,
+    #[arg(long, help = "TOML config file, see config.rs [default: $CONFIG]")]
+    config: Option<PathBuf>,
+    #[arg(long, help = "Network profile from the config file [default: default]")]
+    network: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(Args::parse()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    Telemetry::init(false);
+
+    let env = env::Env::load()?;
+    let config = Config::load(args.config.as_deref().or(env.config_path.as_deref()))?;
+    let network = NetworkProfile {
+        rpc: args.rpc,
+        chain_id: args.chain_id,
+        block: None,
+    }
+    .or(env.network())
+    .or(config.network(args.network.as_deref())?);
+    let prover_settings = ProverSettings {
+        dir: args.dir,
+        serde_format: args
+            .key_format
+            .map(|format| format.parse::<KeyFormat>())
+            .transpose()?,
+    }
+    .or(config.prover.clone());
+
+    let calldata = match (&args.sig, args.calldata) {
+        (Some(sig), _) => abi::encode_call(&abi::parse_signature(sig)?, &args.args)?,
+        (None, Some(calldata)) => calldata,
+        (None, None) => return Err(Error::config("no calldata, pass --calldata or --sig")),
+    };
+    let claim = CallClaim {
+        chain_id: network.chain_id.ok_or_else(|| {
+            Error::config("no chain id, pass --chain-id or add it to a network profile")
+        })?,
+        block_hash: args.block_hash,
+        caller: args.from.unwrap_or_default(),
+        to: args.to,
+        create: args.create,
+        value: args
+            .value
+            .map(|value| abi::parse_value(&value))
+            .transpose()?
+            .unwrap_or_default(),
+        success: !args.reverted,
+        calldata,
+        return_data: args.return_data,
+        assumed_state: match &args.overrides {
+            Some(path) => StateOverride::from_json_file(path)?,
+            None => StateOverride::default(),
+        },
+    };
+
+    let backend = RpcBackend::from_url(&network.rpc.ok_or_else(|| {
+        Error::config("no rpc url, pass --rpc, set ETH_RPC_URL or add it to a network profile")
+    })?)?;
+    let bundle: ProofBundle = serde_json::from_reader(BufReader::new(File::open(&args.bundle)?))?;
+    let proof = bundle
+        .proof
+        .clone()
+        .ok_or_else(|| Error::proof("bundle has no proof, it was only mock verified"))?;
+    let instance = expected_instance_from_backend(&backend, &claim, &bundle).await?;
+
+    let mut prover = RealProver::init(bundle.k, prover_settings.dir())
+        .with_serde_format(prover_settings.serde_format());
+    prover.load_verifier()?;
+    prover.verify(&proof, instance)?;
+    println!(
+        "proof valid for chain {} on top of block {:?}, the call is only compared with the bundle and not attested",
+        claim.chain_id, claim.block_hash
+    );
+    Ok(())
+}
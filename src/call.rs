@@ -0,0 +1,584 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use eth_types::Fr;
+use ethers::signers::LocalWallet;
+#[cfg(feature = "anvil")]
+use ethers::{
+    signers::Signer,
+    types::{transaction::eip2718::TypedTransaction, TransactionRequest},
+};
+use halo2_proofs::SerdeFormat;
+use tracing::{debug, info, warn};
+use zkevm_circuits::util::SubCircuit;
+
+#[cfg(feature = "anvil")]
+use crate::{
+    anvil::{
+        conversion::{Conversion, ConversionReverse},
+        AnvilClientConfig,
+    },
+    inputs_builder::{call_signer, EthCall},
+    BuilderClient,
+};
+use crate::{
+    config::{BlockTag, DEFAULT_K},
+    error::Error,
+    inputs_builder::{CallWitness, CircuitsParams},
+    overrides::StateOverride,
+    prover::{
+        build_circuit, mock_verify, CallCircuit, ProofBundle, RealProver, DEFAULT_CIRCUITS_PARAMS,
+    },
+    public_inputs::PublicInputs,
+    revert::OnRevert,
+    types::zkevm_types::{Address, Bytes, Word},
+    witness::WitnessFile,
+};
+
+/// A call to prove, with the same fields as the call object of `eth_call`
+#[derive(Clone, Debug, Default)]
+pub struct CallRequest {
+    /// caller, has to be the address of the wallet signing the call, see
+    /// `ProverOptions::signer`
+    pub from: Option<Address>,
+    /// `None` for a contract creation, `data` is then the init code
+    pub to: Option<Address>,
+    pub data: Bytes,
+    pub value: Option<Word>,
+    pub gas: Option<Word>,
+    /// block to fork at, `None` and `Latest` use the session's fork
+    pub block: Option<BlockTag>,
+    /// state assumed rather than proven, dropped after the call
+    pub overrides: StateOverride,
+    /// signature with return types to decode the return data with, e.g.
+    /// `balanceOf(address)(uint256)`
+    pub sig: Option<String>,
+    /// whether a revert fails the request or is proven
+    pub on_revert: OnRevert,
+}
+
+impl CallRequest {
+    pub fn new(to: Address, data: Bytes) -> Self {
+        Self {
+            to: Some(to),
+            data,
+            ..Default::default()
+        }
+    }
+
+    /// Contract creation running `init_code`, proven to return the deployed
+    /// runtime code
+    pub fn create(init_code: Bytes) -> Self {
+        Self {
+            data: init_code,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_from(mut self, from: Option<Address>) -> Self {
+        self.from = from;
+        self
+    }
+
+    pub fn with_value(mut self, value: Option<Word>) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn with_gas(mut self, gas: Option<Word>) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn with_block(mut self, block: Option<BlockTag>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: StateOverride) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn with_sig(mut self, sig: Option<String>) -> Self {
+        self.sig = sig;
+        self
+    }
+
+    pub fn with_on_revert(mut self, on_revert: OnRevert) -> Self {
+        self.on_revert = on_revert;
+        self
+    }
+}
+
+/// How calls are proven, shared by every call of a `ProvingSession`
+#[derive(Clone, Debug)]
+pub struct ProverOptions {
+    pub k: u32,
+    pub circuits_params: CircuitsParams,
+    /// only check constraints with the `MockProver`, no proof is generated
+    pub mock: bool,
+    /// directory of the params and keys
+    pub dir: PathBuf,
+    pub serde_format: SerdeFormat,
+    /// sends calls as transactions signed with this wallet, otherwise they
+    /// are traced with `debug_traceCall` and signed with `call_signer`
+    pub signer: Option<LocalWallet>,
+}
+
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            k: DEFAULT_K,
+            circuits_params: DEFAULT_CIRCUITS_PARAMS,
+            mock: false,
+            dir: PathBuf::from("."),
+            serde_format: SerdeFormat::RawBytes,
+            signer: None,
+        }
+    }
+}
+
+impl ProverOptions {
+    pub fn with_k(mut self, k: u32) -> Self {
+        self.k = k;
+        self
+    }
+
+    pub fn with_circuits_params(mut self, circuits_params: CircuitsParams) -> Self {
+        self.circuits_params = circuits_params;
+        self
+    }
+
+    pub fn with_mock(mut self, mock: bool) -> Self {
+        self.mock = mock;
+        self
+    }
+
+    pub fn with_dir(mut self, dir: PathBuf) -> Self {
+        self.dir = dir;
+        self
+    }
+
+    pub fn with_serde_format(mut self, serde_format: SerdeFormat) -> Self {
+        self.serde_format = serde_format;
+        self
+    }
+
+    pub fn with_signer(mut self, signer: Option<LocalWallet>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Reads or generates the params and keys, `None` when mocking
+    pub fn setup_prover(&self, circuit: &CallCircuit) -> Result<Option<RealProver>, Error> {
+        if self.mock {
+            return Ok(None);
+        }
+        let mut prover =
+            RealProver::init(self.k, self.dir.clone()).with_serde_format(self.serde_format);
+        prover.setup_global()?;
+        prover.setup_circuit(circuit.clone())?;
+        Ok(Some(prover))
+    }
+}
+
+/// Prover shared between sessions, jobs and threads. Its params and keys are
+/// read or generated once and kept, all methods block.
+#[derive(Clone)]
+pub struct SharedProver {
+    options: ProverOptions,
+    prover: Arc<Mutex<Option<Arc<RealProver>>>>,
+}
+
+impl SharedProver {
+    pub fn new(options: ProverOptions) -> Self {
+        Self {
+            options,
+            prover: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Reads the params and keys of a previous setup if there are any, so
+    /// that the first call does not wait for them. Without keys they are
+    /// generated by the first `get`.
+    pub fn load(&self) -> Result<(), Error> {
+        if self.options.mock {
+            return Ok(());
+        }
+        let mut cached = self.lock()?;
+        if cached.is_some() {
+            return Ok(());
+        }
+        let mut prover = RealProver::init(self.options.k, self.options.dir.clone())
+            .with_serde_format(self.options.serde_format);
+        prover.setup_global()?;
+        if prover.load_circuit()? {
+            info!(k = self.options.k, "loaded params and keys");
+            *cached = Some(Arc::new(prover));
+        }
+        Ok(())
+    }
+
+    /// The prover, with its keys generated for `circuit` if they were not
+    /// loaded yet. `None` when mocking.
+    pub fn get(&self, circuit: &CallCircuit) -> Result<Option<Arc<RealProver>>, Error> {
+        if self.options.mock {
+            return Ok(None);
+        }
+        let mut cached = self.lock()?;
+        if cached.is_none() {
+            *cached = self.options.setup_prover(circuit)?.map(Arc::new);
+        }
+        Ok(cached.clone())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Option<Arc<RealProver>>>, Error> {
+        self.prover
+            .lock()
+            .map_err(|_| Error::proof("a previous setup of the prover panicked"))
+    }
+}
+
+/// Runs `f` on the blocking thread pool, for keygen and proving which must
+/// not block the async runtime
+pub async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| Error::proof(format!("proving task failed: {err}")))?
+}
+
+/// Circuit, instance and bundle of a call, all that is left is proving it
+pub struct PreparedCall {
+    pub k: u32,
+    pub circuit: CallCircuit,
+    pub instance: Vec<Vec<Fr>>,
+    pub bundle: ProofBundle,
+}
+
+impl PreparedCall {
+    /// Builds the circuit of a witness and its bundle, with `assumed_state`
+    /// being the overrides the witness was generated with
+    pub fn new(
+        mut call_witness: CallWitness,
+        k: u32,
+        assumed_state: StateOverride,
+        sig: Option<&str>,
+    ) -> Result<Self, Error> {
+        let (_, rows_needed) = CallCircuit::min_num_rows_block(&call_witness.witness);
+        let (circuit, instance) = build_circuit(&mut call_witness);
+        info!(k, rows_needed, tx_hash = ?call_witness.tx.hash, "circuit built");
+        debug!(instance = ?instance[0]);
+        match PublicInputs::from_instance(&instance[0]) {
+            Ok(public_inputs) => info!(?public_inputs),
+            Err(err) => warn!(%err, "cannot decode public inputs"),
+        }
+        if !assumed_state.is_empty() {
+            warn!(state_override = ?assumed_state, "assumed state, not proven");
+        }
+
+        let mut bundle = ProofBundle::new(
+            &call_witness,
+            call_witness.inputs.chain_id,
+            k,
+            &instance,
+            assumed_state,
+        )?;
+        if let Some(sig) = sig {
+            bundle.decode_return(sig)?;
+        }
+        Ok(Self {
+            k,
+            circuit,
+            instance,
+            bundle,
+        })
+    }
+
+    /// Proves the call, or only checks its constraints without a prover
+    pub fn prove(self, prover: Option<&RealProver>) -> Result<ProofBundle, Error> {
+        let mut bundle = self.bundle;
+        match prover {
+            None => mock_verify(self.k, &self.circuit, self.instance)?,
+            Some(prover) => bundle.proof = Some(prover.prove(self.circuit, self.instance)?.into()),
+        }
+        Ok(bundle)
+    }
+}
+
+/// A fork along with the prover, so that many calls can be proven without
+/// forking or loading the keys again
+#[cfg(feature = "anvil")]
+pub struct ProvingSession {
+    pub builder: BuilderClient,
+    pub options: ProverOptions,
+    prover: SharedProver,
+}
+
+#[cfg(feature = "anvil")]
+impl ProvingSession {
+    pub async fn new(
+        anvil_config: AnvilClientConfig,
+        options: ProverOptions,
+    ) -> Result<Self, Error> {
+        let builder =
+            BuilderClient::from_anvil_config(options.circuits_params, anvil_config).await?;
+        Ok(Self::from_builder(builder, options))
+    }
+
+    pub fn from_builder(builder: BuilderClient, options: ProverOptions) -> Self {
+        Self {
+            builder,
+            prover: SharedProver::new(options.clone()),
+            options,
+        }
+    }
+
+    /// Forks at the request's block, executes the call with its overrides and
+    /// generates the witness. The overrides are reverted afterwards but kept
+    /// in `builder.state_override` until the next call.
+    pub async fn witness(&mut self, request: &CallRequest) -> Result<CallWitness, Error> {
+        if let Some(BlockTag::Number(block_number)) = request.block {
+            if self.builder.fork_block_number != Some(block_number) {
+                if self.builder.eth_rpc_url.is_none() {
+                    return Err(Error::config(
+                        "cannot fork at another block without an rpc url",
+                    ));
+                }
+                self.builder.reset_fork(block_number).await?;
+            }
+        }
+
+        let snapshot_id = self.builder.backend.snapshot().await?;
+        self.builder.state_override = StateOverride::default();
+        let result = self.witness_unreverted(request).await;
+        self.builder.backend.revert_after(snapshot_id, result).await
+    }
+
+    async fn witness_unreverted(&mut self, request: &CallRequest) -> Result<CallWitness, Error> {
+        if !request.overrides.is_empty() {
+            self.builder
+                .apply_state_override(request.overrides.clone())
+                .await?;
+        }
+        let wallet = match &self.options.signer {
+            Some(wallet) => wallet.clone(),
+            None => call_signer(),
+        };
+        let from: Address = wallet.address().to_zkevm_type();
+        if let Some(requested) = request.from {
+            if requested != from {
+                return Err(Error::config(format!(
+                    "cannot prove a call from {requested:?} without its key, calls are signed by {from:?}"
+                )));
+            }
+        }
+        let value = request.value.unwrap_or_default();
+        // a transaction's caller is funded for the value before estimating,
+        // and for the fees once the gas limit is known
+        if self.options.signer.is_some() {
+            self.builder.fund(from, value).await?;
+        }
+        let gas = match request.gas {
+            Some(gas) => gas,
+            None => {
+                self.builder
+                    .estimate_gas(from, request.to, &request.data, Some(value))
+                    .await?
+            }
+        };
+        let gas_price = self.builder.gas_price().await?;
+        let cost = value + gas * gas_price;
+        let call_witness = match &self.options.signer {
+            None => {
+                // like eth_call the caller is not funded, but the circuit
+                // checks its proven balance pays for the value and the gas
+                let balance = self.builder.backend.get_balance(from, None).await?;
+                if balance < cost {
+                    return Err(Error::config(format!(
+                        "{from:?} has {balance} wei and cannot pay {cost} for the value and gas of the call"
+                    )));
+                }
+                let call = EthCall {
+                    from,
+                    to: request.to,
+                    data: request.data.clone(),
+                    value,
+                    gas,
+                    gas_price,
+                };
+                self.builder.gen_witness_for_call(&call, &wallet).await?
+            }
+            Some(_) => {
+                self.builder.fund(from, cost).await?;
+                let nonce = self.builder.backend.get_nonce(from, None).await?;
+                let mut tx_req = TransactionRequest::new()
+                    .data(request.data.to_anvil_type())
+                    .value(value.to_anvil_type())
+                    .nonce(nonce.to_anvil_type())
+                    .chain_id(self.builder.chain_id.as_u64())
+                    .gas_price(gas_price.to_anvil_type())
+                    .gas(gas.to_anvil_type());
+                if let Some(to) = request.to {
+                    tx_req = tx_req.to(to.to_anvil_type());
+                }
+                let signature = wallet
+                    .sign_transaction(&TypedTransaction::Legacy(tx_req.clone()))
+                    .await
+                    .map_err(|err| Error::config(format!("cannot sign transaction: {err}")))?;
+                let raw_tx = tx_req.rlp_signed(&signature);
+                self.builder
+                    .gen_witness_for_raw_transaction(raw_tx.to_zkevm_type())
+                    .await?
+            }
+        };
+        call_witness.check_gas()?;
+        call_witness.check_revert(request.on_revert)?;
+        Ok(call_witness)
+    }
+
+    /// Builds the circuit and the bundle of a witness from `witness`
+    pub fn prepare(
+        &self,
+        call_witness: CallWitness,
+        sig: Option<&str>,
+    ) -> Result<PreparedCall, Error> {
+        PreparedCall::new(
+            call_witness,
+            self.options.k,
+            self.builder.state_override.clone(),
+            sig,
+        )
+    }
+
+    /// The prover of the session, its params and keys are read or generated
+    /// on first use and kept for the following calls
+    pub fn prover(&self) -> SharedProver {
+        self.prover.clone()
+    }
+
+    /// Reads the params and keys of a previous setup on a blocking thread,
+    /// see `SharedProver::load`
+    pub async fn load_prover(&self) -> Result<(), Error> {
+        let prover = self.prover();
+        blocking(move || prover.load()).await
+    }
+
+    /// Proves a witness from `witness`, or only checks its constraints when
+    /// mocking, in which case the bundle has no proof
+    pub fn prove_witness(
+        &mut self,
+        call_witness: CallWitness,
+        sig: Option<&str>,
+    ) -> Result<ProofBundle, Error> {
+        let prepared = self.prepare(call_witness, sig)?;
+        let prover = self.prover.get(&prepared.circuit)?;
+        prepared.prove(prover.as_deref())
+    }
+
+    pub async fn prove(&mut self, request: &CallRequest) -> Result<ProofBundle, Error> {
+        let call_witness = self.witness(request).await?;
+        self.prove_witness(call_witness, request.sig.as_deref())
+    }
+}
+
+/// Forks, executes and proves a single call. Use a `ProvingSession` to prove
+/// several calls on the same fork.
+#[cfg(feature = "anvil")]
+pub async fn prove_call(
+    anvil_config: AnvilClientConfig,
+    request: &CallRequest,
+    options: ProverOptions,
+) -> Result<ProofBundle, Error> {
+    ProvingSession::new(anvil_config, options)
+        .await?
+        .prove(request)
+        .await
+}
+
+/// Proves a witness file written on another machine, no backend needed
+pub fn prove_witness_file(
+    witness_file: WitnessFile,
+    options: &ProverOptions,
+) -> Result<ProofBundle, Error> {
+    let call_witness = CallWitness::from_inputs(
+        witness_file.inputs,
+        witness_file.tx_hash,
+        options.circuits_params,
+    )?;
+    let prepared = PreparedCall::new(
+        call_witness,
+        options.k,
+        witness_file.assumed_state,
+        witness_file.signature.as_deref(),
+    )?;
+    let prover = options.setup_prover(&prepared.circuit)?;
+    prepared.prove(prover.as_ref())
+}
+
+#[cfg(all(test, feature = "anvil"))]
+pub(crate) mod tests {
+    use ethers::signers::Signer;
+    use ethers_core::utils::keccak256;
+
+    use super::{CallRequest, ProverOptions, ProvingSession};
+    use crate::{
+        anvil::{conversion::Conversion, AnvilClientConfig},
+        inputs_builder::call_signer,
+        types::zkevm_types::{Address, Bytes, Word, H256},
+    };
+
+    /// Contract returning its storage slot 0, which holds 42
+    pub(crate) const STORAGE_READER: Address = Address::repeat_byte(0x35);
+
+    /// Session on a local node with `STORAGE_READER` deployed, only checking
+    /// constraints
+    pub(crate) async fn local_session() -> ProvingSession {
+        let session = ProvingSession::new(
+            AnvilClientConfig::default(),
+            ProverOptions::default().with_mock(true),
+        )
+        .await
+        .unwrap();
+        let backend = &session.builder.backend;
+        // PUSH1 0 SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let code = vec![
+            0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+        ];
+        backend
+            .set_code(STORAGE_READER, Bytes::from(code))
+            .await
+            .unwrap();
+        backend
+            .set_storage_at(STORAGE_READER, Word::zero(), H256::from_low_u64_be(42))
+            .await
+            .unwrap();
+        backend.mine_one().await;
+        session
+    }
+
+    #[tokio::test]
+    async fn test_traced_call() {
+        let mut session = local_session().await;
+        let request = CallRequest::new(STORAGE_READER, Bytes::default());
+        let call_witness = session.witness(&request).await.unwrap();
+
+        let tx = &call_witness.tx;
+        let signer: Address = call_signer().address().to_zkevm_type();
+        assert_eq!(tx.from, signer);
+        assert_eq!(H256::from(keccak256(tx.rlp())), tx.hash);
+        assert_eq!(
+            call_witness.return_data().unwrap(),
+            Bytes::from(H256::from_low_u64_be(42).as_bytes().to_vec())
+        );
+        // checks the constraints, the tx circuit included
+        session.prove_witness(call_witness, None).unwrap();
+
+        let request = request.with_from(Some(Address::repeat_byte(1)));
+        assert!(session.witness(&request).await.is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use std::{collections::BTreeMap, fs, path::Path, path::PathBuf};
+
+use halo2_proofs::SerdeFormat;
+use serde::Deserialize;
+
+use crate::{
+    error::Error,
+    inputs_builder::CircuitsParams,
+    prover::{DEFAULT_CIRCUITS_PARAMS, MAX_CALLDATA, MAX_TXS},
+    types::zkevm_types::Word,
+};
+
+pub const DEFAULT_K: u32 = 19;
+/// largest degree the KZG setup over bn256 supports
+pub const MAX_K: u32 = 28;
+
+/// Block to fork at, either the latest one or a fixed number
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "BlockTagRepr")]
+pub enum BlockTag {
+    #[default]
+    Latest,
+    Number(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BlockTagRepr {
+    Number(usize),
+    Tag(String),
+}
+
+impl TryFrom<BlockTagRepr> for BlockTag {
+    type Error = Error;
+
+    fn try_from(repr: BlockTagRepr) -> Result<Self, Error> {
+        match repr {
+            BlockTagRepr::Number(number) => Ok(BlockTag::Number(number)),
+            BlockTagRepr::Tag(tag) => tag.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for BlockTag {
+    type Err = Error;
+
+    fn from_str(tag: &str) -> Result<Self, Error> {
+        if tag == "latest" {
+            return Ok(BlockTag::Latest);
+        }
+        let number = match tag.strip_prefix("0x") {
+            Some(hex) => usize::from_str_radix(hex, 16),
+            None => tag.parse(),
+        };
+        number
+            .map(BlockTag::Number)
+            .map_err(|_| Error::config(format!("invalid block tag {tag}")))
+    }
+}
+
+impl BlockTag {
+    /// Fork block number as taken by `AnvilClientConfig`, `None` being latest
+    pub fn block_number(&self) -> Option<usize> {
+        match self {
+            BlockTag::Latest => None,
+            BlockTag::Number(number) => Some(*number),
+        }
+    }
+}
+
+/// Chain to fork. Every field is optional so that profiles coming from the
+/// CLI, the environment and the config file can be layered with `or`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    pub rpc: Option<String>,
+    /// checked against the chain id reported by the node
+    pub chain_id: Option<u64>,
+    pub block: Option<BlockTag>,
+}
+
+impl NetworkProfile {
+    /// Fills the fields missing in `self` from `fallback`
+    pub fn or(self, fallback: NetworkProfile) -> NetworkProfile {
+        NetworkProfile {
+            rpc: self.rpc.or(fallback.rpc),
+            chain_id: self.chain_id.or(fallback.chain_id),
+            block: self.block.or(fallback.block),
+        }
+    }
+
+    pub fn fork_block_number(&self) -> Option<usize> {
+        self.block.unwrap_or_default().block_number()
+    }
+
+    pub fn check_chain_id(&self, chain_id: Word) -> Result<(), Error> {
+        match self.chain_id {
+            Some(expected) if Word::from(expected) != chain_id => Err(Error::config(format!(
+                "expected chain id {expected} but the node reports {chain_id}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Circuit sizes and degree, unset fields default to `DEFAULT_CIRCUITS_PARAMS`
+/// and `DEFAULT_K`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitProfile {
+    pub k: Option<u32>,
+    /// fixed by the circuit type, only accepted to be validated
+    pub max_txs: Option<usize>,
+    /// fixed by the circuit type, only accepted to be validated
+    pub max_calldata: Option<usize>,
+    pub max_rws: Option<usize>,
+    pub max_copy_rows: Option<usize>,
+    pub max_exp_steps: Option<usize>,
+    pub max_bytecode: Option<usize>,
+    pub max_evm_rows: Option<usize>,
+    pub max_keccak_rows: Option<usize>,
+}
+
+impl CircuitProfile {
+    pub fn or(self, fallback: CircuitProfile) -> CircuitProfile {
+        CircuitProfile {
+            k: self.k.or(fallback.k),
+            max_txs: self.max_txs.or(fallback.max_txs),
+            max_calldata: self.max_calldata.or(fallback.max_calldata),
+            max_rws: self.max_rws.or(fallback.max_rws),
+            max_copy_rows: self.max_copy_rows.or(fallback.max_copy_rows),
+            max_exp_steps: self.max_exp_steps.or(fallback.max_exp_steps),
+            max_bytecode: self.max_bytecode.or(fallback.max_bytecode),
+            max_evm_rows: self.max_evm_rows.or(fallback.max_evm_rows),
+            max_keccak_rows: self.max_keccak_rows.or(fallback.max_keccak_rows),
+        }
+    }
+
+    pub fn k(&self) -> Result<u32, Error> {
+        let k = self.k.unwrap_or(DEFAULT_K);
+        if k == 0 || k > MAX_K {
+            return Err(Error::config(format!(
+                "k must be between 1 and {MAX_K}, got {k}"
+            )));
+        }
+        Ok(k)
+    }
+
+    pub fn circuits_params(&self) -> Result<CircuitsParams, Error> {
+        if let Some(max_txs) = self.max_txs.filter(|max_txs| *max_txs != MAX_TXS) {
+            return Err(Error::config(format!(
+                "max_txs is fixed to {MAX_TXS} by the circuit, got {max_txs}"
+            )));
+        }
+        if let Some(max_calldata) = self
+            .max_calldata
+            .filter(|max_calldata| *max_calldata != MAX_CALLDATA)
+        {
+            return Err(Error::config(format!(
+                "max_calldata is fixed to {MAX_CALLDATA} by the circuit, got {max_calldata}"
+            )));
+        }
+        let defaults = DEFAULT_CIRCUITS_PARAMS;
+        let params = CircuitsParams {
+            max_rws: self.max_rws.unwrap_or(defaults.max_rws),
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_copy_rows: self.max_copy_rows.unwrap_or(defaults.max_copy_rows),
+            max_exp_steps: self.max_exp_steps.unwrap_or(defaults.max_exp_steps),
+            max_bytecode: self.max_bytecode.unwrap_or(defaults.max_bytecode),
+            max_evm_rows: self.max_evm_rows.unwrap_or(defaults.max_evm_rows),
+            max_keccak_rows: self.max_keccak_rows.unwrap_or(defaults.max_keccak_rows),
+        };
+        for (name, value) in [
+            ("max_rws", params.max_rws),
+            ("max_copy_rows", params.max_copy_rows),
+            ("max_exp_steps", params.max_exp_steps),
+            ("max_bytecode", params.max_bytecode),
+            ("max_evm_rows", params.max_evm_rows),
+            ("max_keccak_rows", params.max_keccak_rows),
+        ] {
+            if value == 0 {
+                return Err(Error::config(format!("{name} must not be zero")));
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Serialization of proving keys and params, see halo2's `SerdeFormat`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyFormat {
+    Processed,
+    #[default]
+    RawBytes,
+    RawBytesUnchecked,
+}
+
+impl std::str::FromStr for KeyFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Error> {
+        match format.replace('-', "_").as_str() {
+            "processed" => Ok(KeyFormat::Processed),
+            "raw_bytes" => Ok(KeyFormat::RawBytes),
+            "raw_bytes_unchecked" => Ok(KeyFormat::RawBytesUnchecked),
+            _ => Err(Error::config(format!(
+                "invalid key format {format}, expected processed, raw_bytes or raw_bytes_unchecked"
+            ))),
+        }
+    }
+}
+
+impl From<KeyFormat> for SerdeFormat {
+    fn from(format: KeyFormat) -> Self {
+        match format {
+            KeyFormat::Processed => SerdeFormat::Processed,
+            KeyFormat::RawBytes => SerdeFormat::RawBytes,
+            KeyFormat::RawBytesUnchecked => SerdeFormat::RawBytesUnchecked,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProverSettings {
+    /// directory for params, keys and proofs
+    pub dir: Option<PathBuf>,
+    pub serde_format: Option<KeyFormat>,
+}
+
+impl ProverSettings {
+    pub fn or(self, fallback: ProverSettings) -> ProverSettings {
+        ProverSettings {
+            dir: self.dir.or(fallback.dir),
+            serde_format: self.serde_format.or(fallback.serde_format),
+        }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.dir.clone().unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    pub fn serde_format(&self) -> SerdeFormat {
+        self.serde_format.unwrap_or_default().into()
+    }
+}
+
+/// Contents of the TOML config file. Profiles named `default` are used when
+/// no profile is selected. Settings are resolved from CLI flags first, then
+/// the environment (see `env::Env`), then the config file.
+///
+/// ```toml
+/// [networks.sepolia]
+/// rpc = "https://rpc.sepolia.org"
+/// chain_id = 11155111
+/// block = 4363656
+///
+/// [circuits.default]
+/// k = 19
+/// max_rws = 1000
+///
+/// [prover]
+/// dir = "keys"
+/// serde_format = "raw_bytes"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkProfile>,
+    #[serde(default)]
+    pub circuits: BTreeMap<String, CircuitProfile>,
+    #[serde(default)]
+    pub prover: ProverSettings,
+}
+
+impl Config {
+    pub fn from_toml_str(contents: &str) -> Result<Self, Error> {
+        toml::from_str(contents).map_err(|err| Error::config(err.to_string()))
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| Error::config(format!("cannot read {}: {err}", path.display())))?;
+        Self::from_toml_str(&contents)
+    }
+
+    /// Reads the config file if there is one, otherwise every profile is empty
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        path.map_or_else(|| Ok(Config::default()), Self::from_toml_file)
+    }
+
+    /// Looks up a network profile, an unknown name is an error while a missing
+    /// `default` profile just resolves to an empty one
+    pub fn network(&self, name: Option<&str>) -> Result<NetworkProfile, Error> {
+        profile(&self.networks, "network", name)
+    }
+
+    pub fn circuit(&self, name: Option<&str>) -> Result<CircuitProfile, Error> {
+        profile(&self.circuits, "circuit", name)
+    }
+}
+
+fn profile<T: Clone + Default>(
+    profiles: &BTreeMap<String, T>,
+    kind: &str,
+    name: Option<&str>,
+) -> Result<T, Error> {
+    match name {
+        Some(name) => profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::config(format!("unknown {kind} profile {name}"))),
+        None => Ok(profiles.get("default").cloned().unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockTag, CircuitProfile, Config, KeyFormat, NetworkProfile};
+    use crate::prover::{DEFAULT_CIRCUITS_PARAMS, MAX_TXS};
+
+    #[test]
+    fn test_profiles() {
+        let config = Config::from_toml_str(
+            r#"
+            [networks.default]
+            rpc = "http://localhost:8545"
+            block = "latest"
+
+            [networks.sepolia]
+            rpc = "https://rpc.sepolia.org"
+            chain_id = 11155111
+            block = "0x429588"
+
+            [circuits.small]
+            k = 18
+            max_rws = 500
+
+            [prover]
+            serde_format = "processed"
+            "#,
+        )
+        .unwrap();
+
+        let sepolia = config.network(Some("sepolia")).unwrap();
+        assert_eq!(sepolia.block, Some(BlockTag::Number(4363656)));
+        assert_eq!(config.network(None).unwrap().block, Some(BlockTag::Latest));
+        assert!(config.network(Some("mainnet")).is_err());
+
+        // cli flags take precedence over the profile
+        let cli = NetworkProfile {
+            block: Some(BlockTag::Number(1)),
+            ..Default::default()
+        };
+        let network = cli.or(sepolia);
+        assert_eq!(network.fork_block_number(), Some(1));
+        assert_eq!(network.rpc.as_deref(), Some("https://rpc.sepolia.org"));
+        assert!(network.check_chain_id(11155111.into()).is_ok());
+        assert!(network.check_chain_id(1.into()).is_err());
+
+        let circuit = config.circuit(Some("small")).unwrap();
+        assert_eq!(circuit.k().unwrap(), 18);
+        let params = circuit.circuits_params().unwrap();
+        assert_eq!(params.max_rws, 500);
+        assert_eq!(params.max_bytecode, DEFAULT_CIRCUITS_PARAMS.max_bytecode);
+        assert_eq!(config.prover.serde_format, Some(KeyFormat::Processed));
+        assert_eq!(
+            "raw-bytes-unchecked".parse::<KeyFormat>().unwrap(),
+            KeyFormat::RawBytesUnchecked
+        );
+        assert!("fast".parse::<KeyFormat>().is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        let circuit = CircuitProfile {
+            max_txs: Some(MAX_TXS + 1),
+            ..Default::default()
+        };
+        assert!(circuit.circuits_params().is_err());
+
+        let circuit = CircuitProfile {
+            k: Some(40),
+            ..Default::default()
+        };
+        assert!(circuit.k().is_err());
+
+        assert!(Config::from_toml_str("[networks.default]\nrpcs = \"\"").is_err());
+        assert!(Config::from_toml_str("[networks.default]\nblock = \"safe\"").is_err());
+    }
+}
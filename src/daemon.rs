@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::{
+    batch::BatchCall,
+    call::{blocking, CallRequest, ProvingSession},
+    error::Error,
+    jobs::{JobStatus, JobStore},
+    prover::ProofBundle,
+};
+
+/// Long running prover, keeping the fork, params and keys in memory between
+/// jobs. Witnesses are generated one at a time on the shared fork, at most
+/// `max_concurrent` jobs are proven at once.
+pub struct Daemon {
+    session: Mutex<ProvingSession>,
+    jobs: JobStore,
+    permits: Arc<Semaphore>,
+}
+
+impl Daemon {
+    pub fn new(session: ProvingSession, jobs: JobStore, max_concurrent: usize) -> Self {
+        Self {
+            session: Mutex::new(session),
+            jobs,
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    /// Queues a call, returns the job id right away
+    pub fn submit(self: &Arc<Self>, request: CallRequest) -> u64 {
+        let id = self.jobs.create();
+        let daemon = self.clone();
+        tokio::spawn(
+            async move {
+                let status = match daemon.run(id, request).await {
+                    Ok(bundle) => {
+                        info!("job done");
+                        JobStatus::Done {
+                            result: bundle.return_data.clone(),
+                            proof: bundle,
+                        }
+                    }
+                    Err(err) => {
+                        warn!(%err, "job failed");
+                        JobStatus::Failed {
+                            error: err.to_string(),
+                        }
+                    }
+                };
+                // ignored if the job was cancelled in the meantime
+                daemon.jobs.update(id, status);
+            }
+            .instrument(info_span!("job", id)),
+        );
+        id
+    }
+
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        self.jobs.get(id)
+    }
+
+    /// Cancels a job that has not finished yet. A job is only stopped before
+    /// its witness is generated or before proving, a proof already running
+    /// completes and is discarded.
+    pub fn cancel(&self, id: u64) -> Option<bool> {
+        self.jobs.cancel(id)
+    }
+
+    fn check_cancelled(&self, id: u64) -> Result<(), Error> {
+        match self.jobs.get(id) {
+            Some(JobStatus::Cancelled) => Err(Error::proof("job cancelled")),
+            _ => Ok(()),
+        }
+    }
+
+    async fn run(&self, id: u64, request: CallRequest) -> Result<ProofBundle, Error> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::proof("daemon shut down"))?;
+        self.check_cancelled(id)?;
+        self.jobs.update(id, JobStatus::Running);
+
+        let (prepared, prover) = {
+            let mut session = self.session.lock().await;
+            self.check_cancelled(id)?;
+            let call_witness = session.witness(&request).await?;
+            let prepared = session.prepare(call_witness, request.sig.as_deref())?;
+            (prepared, session.prover())
+        };
+        self.check_cancelled(id)?;
+
+        // keys missing at startup are generated by the first job, off the
+        // runtime and without holding the session
+        blocking(move || {
+            let _permit = permit;
+            let prover = prover.get(&prepared.circuit)?;
+            prepared.prove(prover.as_deref())
+        })
+        .await
+    }
+}
+
+type Response = (StatusCode, Json<Value>);
+
+fn error_response(status: StatusCode, error: impl ToString) -> Response {
+    (status, Json(json!({ "error": error.to_string() })))
+}
+
+async fn submit(State(daemon): State<Arc<Daemon>>, Json(call): Json<BatchCall>) -> Response {
+    match call.into_request() {
+        Ok(request) => (
+            StatusCode::ACCEPTED,
+            Json(json!({ "jobId": daemon.submit(request) })),
+        ),
+        Err(err) => error_response(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+async fn status(State(daemon): State<Arc<Daemon>>, Path(id): Path<u64>) -> Response {
+    match daemon.status(id) {
+        Some(status) => (StatusCode::OK, Json(json!(status))),
+        None => error_response(StatusCode::NOT_FOUND, "unknown job id"),
+    }
+}
+
+async fn result(State(daemon): State<Arc<Daemon>>, Path(id): Path<u64>) -> Response {
+    match daemon.status(id) {
+        Some(JobStatus::Done { proof, .. }) => (StatusCode::OK, Json(json!(proof))),
+        Some(status) => (StatusCode::CONFLICT, Json(json!(status))),
+        None => error_response(StatusCode::NOT_FOUND, "unknown job id"),
+    }
+}
+
+async fn cancel(State(daemon): State<Arc<Daemon>>, Path(id): Path<u64>) -> Response {
+    match daemon.cancel(id) {
+        Some(true) => (StatusCode::OK, Json(json!({ "cancelled": true }))),
+        Some(false) => error_response(StatusCode::CONFLICT, "job already finished"),
+        None => error_response(StatusCode::NOT_FOUND, "unknown job id"),
+    }
+}
+
+/// HTTP API of the daemon:
+///
+/// - `POST /jobs` with a call as in a batch file, returns `{"jobId": id}`
+/// - `GET /jobs/:id` returns the job's status
+/// - `GET /jobs/:id/result` returns the proof bundle once done
+/// - `POST /jobs/:id/cancel`
+pub fn router(daemon: Arc<Daemon>) -> Router {
+    Router::new()
+        .route("/jobs", post(submit))
+        .route("/jobs/:id", get(status))
+        .route("/jobs/:id/result", get(result))
+        .route("/jobs/:id/cancel", post(cancel))
+        .with_state(daemon)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::Daemon;
+    use crate::{
+        call::{
+            tests::{local_session, STORAGE_READER},
+            CallRequest,
+        },
+        jobs::{JobStatus, JobStore},
+        types::zkevm_types::{Bytes, H256},
+    };
+
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let session = local_session().await;
+        session.load_prover().await.unwrap();
+        let daemon = Arc::new(Daemon::new(session, JobStore::default(), 1));
+
+        let id = daemon.submit(CallRequest::new(STORAGE_READER, Bytes::default()));
+        let status = tokio::time::timeout(Duration::from_secs(60), async {
+            loop {
+                match daemon.status(id) {
+                    Some(status) if status.is_finished() => return status,
+                    _ => tokio::time::sleep(Duration::from_millis(50)).await,
+                }
+            }
+        })
+        .await
+        .unwrap();
+        match status {
+            JobStatus::Done { result, proof } => {
+                assert_eq!(
+                    result,
+                    Bytes::from(H256::from_low_u64_be(42).as_bytes().to_vec())
+                );
+                assert!(proof.success);
+            }
+            status => panic!("job did not finish: {status:?}"),
+        }
+        assert_eq!(daemon.cancel(id), Some(false));
+        assert_eq!(daemon.cancel(id + 1), None);
+    }
+}
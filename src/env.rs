@@ -1,35 +1,49 @@
 use dotenv::dotenv;
-use ethers::types::U64;
-use std::env;
+use std::{env, path::PathBuf};
+
+use crate::{
+    config::{BlockTag, NetworkProfile},
+    error::Error,
+};
 
-#[allow(dead_code)]
 pub struct Env {
     pub eth_rpc_url: Option<String>,
     pub fork_block_number: Option<usize>,
     pub signing_key: Option<String>,
+    /// path of the TOML config file, see `config::Config`
+    pub config_path: Option<PathBuf>,
+}
+
+/// Reads a variable, treating empty values like in the sample `.env` as unset
+fn var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|val| !val.is_empty())
 }
 
-#[allow(dead_code)]
 impl Env {
-    pub fn load() -> Env {
+    pub fn load() -> Result<Env, Error> {
         dotenv().ok();
-        let eth_rpc_url = match env::var("ETH_RPC_URL") {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
-        let fork_block_number = match env::var("FORK_BLOCK_NUMBER") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let signing_key = match env::var("SK") {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
+        let fork_block_number = var("FORK_BLOCK_NUMBER")
+            .map(|val| {
+                val.parse::<usize>()
+                    .map_err(|_| Error::config(format!("invalid FORK_BLOCK_NUMBER {val}")))
+            })
+            .transpose()?;
 
-        Env {
-            eth_rpc_url,
+        Ok(Env {
+            eth_rpc_url: var("ETH_RPC_URL"),
             fork_block_number,
-            signing_key,
+            signing_key: var("SK"),
+            config_path: var("CONFIG").map(PathBuf::from),
+        })
+    }
+
+    /// Network settings from the environment, these take precedence over the
+    /// config file but not over CLI flags
+    pub fn network(&self) -> NetworkProfile {
+        NetworkProfile {
+            rpc: self.eth_rpc_url.clone(),
+            chain_id: None,
+            block: self.fork_block_number.map(BlockTag::Number),
         }
     }
 }
@@ -1,26 +1,201 @@
+#[cfg(feature = "anvil")]
 use anvil::eth::error::BlockchainError;
+use eth_types::{Address, Word, H256};
+use ethers::providers::ProviderError;
 use ethers_core::utils::rlp;
 use halo2_proofs::plonk;
+use std::{fmt, time::Duration};
 // use partial_mpt;
 
-#[allow(dead_code)]
+/// Where an error happened, printed after the error message
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<H256>,
+    pub address: Option<Address>,
+    pub slot: Option<Word>,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(block_number) = self.block_number {
+            parts.push(format!("block {block_number}"));
+        }
+        if let Some(tx_hash) = self.tx_hash {
+            parts.push(format!("tx {tx_hash:?}"));
+        }
+        if let Some(address) = self.address {
+            parts.push(format!("address {address:?}"));
+        }
+        if let Some(slot) = self.slot {
+            parts.push(format!("slot {slot:#x}"));
+        }
+        if !parts.is_empty() {
+            write!(f, " ({})", parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
+    #[cfg(feature = "anvil")]
     AnvilError(Box<BlockchainError>),
+    ProviderError(Box<ProviderError>),
     RlpDecoderError(Box<rlp::DecoderError>),
     BusMappingError(Box<bus_mapping::Error>),
     Halo2Error(Box<plonk::Error>),
     StdError(Box<std::io::Error>),
+    SerdeJsonError(Box<serde_json::Error>),
     // PartialMptError(Box<partial_mpt::Error>),
-    InternalError(&'static str),
+    TimeoutError(Duration),
+    AbiError(Box<ethers::abi::Error>),
+    /// missing or malformed data returned by the node or backend
+    RpcError(String, ErrorContext),
+    /// value that cannot be represented in the other type universe
+    ConversionError(String, ErrorContext),
+    /// invalid configuration or CLI arguments
+    ConfigError(String),
+    /// state or storage proofs that do not match the state root
+    MptError(String, ErrorContext),
+    /// inputs the circuit input builder cannot build a witness from
+    WitnessError(String, ErrorContext),
+    /// key generation, proving or verification failures
+    ProofError(String),
 }
 
+impl Error {
+    pub fn rpc(message: impl Into<String>) -> Self {
+        Error::RpcError(message.into(), ErrorContext::default())
+    }
+
+    pub fn conversion(message: impl Into<String>) -> Self {
+        Error::ConversionError(message.into(), ErrorContext::default())
+    }
+
+    pub fn config(message: impl Into<String>) -> Self {
+        Error::ConfigError(message.into())
+    }
+
+    pub fn mpt(message: impl Into<String>) -> Self {
+        Error::MptError(message.into(), ErrorContext::default())
+    }
+
+    pub fn witness(message: impl Into<String>) -> Self {
+        Error::WitnessError(message.into(), ErrorContext::default())
+    }
+
+    pub fn proof(message: impl Into<String>) -> Self {
+        Error::ProofError(message.into())
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::RpcError(_, context)
+            | Error::ConversionError(_, context)
+            | Error::MptError(_, context)
+            | Error::WitnessError(_, context) => Some(context),
+            _ => None,
+        }
+    }
+
+    fn context_mut(&mut self) -> Option<&mut ErrorContext> {
+        match self {
+            Error::RpcError(_, context)
+            | Error::ConversionError(_, context)
+            | Error::MptError(_, context)
+            | Error::WitnessError(_, context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Adds the block number to the context, ignored by variants without one
+    pub fn with_block(mut self, block_number: impl TryInto<u64>) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.block_number = block_number.try_into().ok();
+        }
+        self
+    }
+
+    pub fn with_tx(mut self, tx_hash: H256) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.tx_hash = Some(tx_hash);
+        }
+        self
+    }
+
+    pub fn with_address(mut self, address: Address) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.address = Some(address);
+        }
+        self
+    }
+
+    pub fn with_slot(mut self, slot: Word) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.slot = Some(slot);
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "anvil")]
+            Error::AnvilError(err) => write!(f, "anvil error: {err}"),
+            Error::ProviderError(err) => write!(f, "provider error: {err}"),
+            Error::RlpDecoderError(err) => write!(f, "rlp decoder error: {err}"),
+            Error::BusMappingError(err) => write!(f, "bus-mapping error: {err:?}"),
+            Error::Halo2Error(err) => write!(f, "halo2 error: {err}"),
+            Error::StdError(err) => write!(f, "io error: {err}"),
+            Error::SerdeJsonError(err) => write!(f, "json error: {err}"),
+            Error::TimeoutError(timeout) => write!(f, "timed out after {timeout:?}"),
+            Error::AbiError(err) => write!(f, "abi error: {err}"),
+            Error::RpcError(message, context) => write!(f, "rpc error: {message}{context}"),
+            Error::ConversionError(message, context) => {
+                write!(f, "conversion error: {message}{context}")
+            }
+            Error::ConfigError(message) => write!(f, "config error: {message}"),
+            Error::MptError(message, context) => write!(f, "mpt error: {message}{context}"),
+            Error::WitnessError(message, context) => {
+                write!(f, "witness error: {message}{context}")
+            }
+            Error::ProofError(message) => write!(f, "proof error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "anvil")]
+            Error::AnvilError(err) => Some(err.as_ref()),
+            Error::ProviderError(err) => Some(err.as_ref()),
+            Error::RlpDecoderError(err) => Some(err.as_ref()),
+            Error::Halo2Error(err) => Some(err.as_ref()),
+            Error::StdError(err) => Some(err.as_ref()),
+            Error::SerdeJsonError(err) => Some(err.as_ref()),
+            Error::AbiError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "anvil")]
 impl From<BlockchainError> for Error {
     fn from(err: BlockchainError) -> Self {
         Error::AnvilError(Box::new(err))
     }
 }
 
+impl From<ProviderError> for Error {
+    fn from(err: ProviderError) -> Self {
+        Error::ProviderError(Box::new(err))
+    }
+}
+
 impl From<bus_mapping::Error> for Error {
     fn from(err: bus_mapping::Error) -> Self {
         Error::BusMappingError(Box::new(err))
@@ -50,3 +225,38 @@ impl From<std::io::Error> for Error {
         Error::StdError(Box::new(err))
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::SerdeJsonError(Box::new(err))
+    }
+}
+
+impl From<ethers::abi::Error> for Error {
+    fn from(err: ethers::abi::Error) -> Self {
+        Error::AbiError(Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use eth_types::{Address, Word};
+
+    #[test]
+    fn test_display_with_context() {
+        let err = Error::rpc("block not found").with_block(42usize);
+        assert_eq!(err.to_string(), "rpc error: block not found (block 42)");
+
+        let err = Error::mpt("invalid storage proof")
+            .with_address(Address::zero())
+            .with_slot(Word::from(1));
+        assert_eq!(
+            err.to_string(),
+            "mpt error: invalid storage proof (address 0x0000000000000000000000000000000000000000, slot 0x1)"
+        );
+
+        let err = Error::config("k too small").with_block(1u64);
+        assert_eq!(err.to_string(), "config error: k too small");
+    }
+}
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    panic::AssertUnwindSafe,
+    str::FromStr,
+};
 
 use axiom_eth::{providers::get_block_storage_input_async, storage::EthBlockStorageInput};
 pub use bus_mapping::{
@@ -10,41 +14,79 @@ pub use bus_mapping::{
     state_db::{CodeDB, StateDB},
 };
 use eth_types::{BigEndianHash, Fr};
+#[cfg(feature = "anvil")]
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip2718::TypedTransaction, TransactionRequest},
+    utils::keccak256,
+};
 use ethers_providers::Provider;
 
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 // use partial_mpt::StateTrie;
 use zkevm_circuits::witness::block_convert;
 
-use futures::future;
+use futures::{future, FutureExt};
+use tracing::{debug, info_span, instrument};
+#[cfg(feature = "anvil")]
+use tracing::{warn, Instrument};
 
 use crate::types::zkevm_types::*;
-use crate::{anvil::AnvilClient, error::Error};
+#[cfg(feature = "anvil")]
+use crate::{
+    anvil::{
+        conversion::{Conversion, ConversionReverse},
+        AnvilClient, AnvilClientConfig, WaitOptions,
+    },
+    overrides::AccountOverride,
+    types::anvil_types,
+};
+use crate::{
+    backend::{ChainBackend, DefaultBackend},
+    error::Error,
+    overrides::StateOverride,
+    revert::{OnRevert, RevertReason},
+    trace::PrestateTrace,
+};
 
-#[allow(dead_code)]
-pub struct BuilderClient {
-    pub anvil: AnvilClient,
+pub struct BuilderClient<B: ChainBackend = DefaultBackend> {
+    pub backend: B,
     pub chain_id: eth_types::Word,
     pub circuits_params: CircuitsParams,
     pub eth_rpc_url: Option<String>,
     pub fork_block_number: Option<usize>,
+    pub state_override: StateOverride,
+}
+
+/// The block a block is executed on top of, the genesis block has none
+fn parent_block_number(block_number: usize) -> Result<usize, Error> {
+    block_number.checked_sub(1).ok_or_else(|| {
+        Error::witness("the genesis block has no parent state").with_block(block_number)
+    })
 }
 
 pub fn get_state_accesses(
     block: &EthBlockFull,
     geth_traces: &[eth_types::GethExecTrace],
 ) -> Result<AccessSet, Error> {
+    let with_block = |err: Error| match block.number {
+        Some(block_number) => err.with_block(block_number.as_u64()),
+        None => err,
+    };
     let mut block_access_trace = vec![Access::new(
         None,
         RW::WRITE,
         AccessValue::Account {
             address: block
                 .author
-                .ok_or(Error::InternalError("Incomplete block"))?,
+                .ok_or_else(|| with_block(Error::rpc("block without author")))?,
         },
     )];
     for (tx_index, tx) in block.transactions.iter().enumerate() {
-        let geth_trace = &geth_traces[tx_index];
+        let geth_trace = geth_traces.get(tx_index).ok_or_else(|| {
+            with_block(Error::witness("missing transaction trace").with_tx(tx.hash))
+        })?;
         let tx_access_trace = gen_state_access_trace(block, tx, geth_trace)?;
         block_access_trace.extend(tx_access_trace);
     }
@@ -52,8 +94,336 @@ pub fn get_state_accesses(
     Ok(AccessSet::from(block_access_trace))
 }
 
-#[allow(dead_code)]
-impl BuilderClient {
+/// Private key of the first anvil dev account, traced calls are signed with
+/// it when no other wallet is given
+#[cfg(feature = "anvil")]
+pub const CALL_SIGNER_KEY: &str =
+    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Wallet of `CALL_SIGNER_KEY`
+#[cfg(feature = "anvil")]
+pub fn call_signer() -> LocalWallet {
+    CALL_SIGNER_KEY
+        .parse()
+        .expect("CALL_SIGNER_KEY is a valid private key")
+}
+
+/// An `eth_call` to prove, executed on top of the latest block of the fork
+#[derive(Clone, Debug, Default)]
+pub struct EthCall {
+    pub from: Address,
+    /// `None` for a contract creation
+    pub to: Option<Address>,
+    pub data: Bytes,
+    pub value: Word,
+    pub gas: Word,
+    /// legacy gas price, see `gas_price`
+    pub gas_price: Word,
+}
+
+/// Everything the circuit input builder needs for a block, fetched from the
+/// backend. Serializable so that the witness can be built without a backend,
+/// see `WitnessFile`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WitnessInputs {
+    pub chain_id: Word,
+    pub block: EthBlockFull,
+    pub traces: Vec<GethExecTrace>,
+    pub history_hashes: Vec<Word>,
+    pub prev_state_root: Word,
+    /// proofs of the accessed accounts and storage at the parent block
+    pub proofs: Vec<EIP1186ProofResponse>,
+    pub codes: BTreeMap<Address, Bytes>,
+    pub axiom_inputs: EthBlockStorageInput,
+}
+
+impl WitnessInputs {
+    #[instrument(name = "circuit_inputs", skip_all, fields(txs = self.traces.len()))]
+    pub fn circuit_input_builder(
+        &self,
+        circuits_params: CircuitsParams,
+    ) -> Result<CircuitInputBuilder, Error> {
+        let codes = self
+            .codes
+            .iter()
+            .map(|(address, code)| (*address, code.to_vec()))
+            .collect();
+        let (state_db, code_db) = build_state_code_db(self.proofs.clone(), codes);
+        let block = Block::new(
+            self.chain_id,
+            self.history_hashes.clone(),
+            self.prev_state_root,
+            &self.block,
+            circuits_params,
+            self.axiom_inputs.clone(),
+        )?;
+        let mut builder = CircuitInputBuilder::new(state_db, code_db, block);
+        builder.handle_block(&self.block, &self.traces)?;
+        Ok(builder)
+    }
+
+    pub fn witness(
+        &self,
+        circuits_params: CircuitsParams,
+    ) -> Result<zkevm_circuits::witness::Block<Fr>, Error> {
+        let builder = self.circuit_input_builder(circuits_params)?;
+        info_span!("witness_conversion")
+            .in_scope(|| Ok(block_convert::<Fr>(&builder.block, &builder.code_db)?))
+    }
+}
+
+/// Witness of a block proving a single call, along with the call's transaction and trace
+pub struct CallWitness {
+    pub tx: Transaction,
+    pub trace: GethExecTrace,
+    pub witness: zkevm_circuits::witness::Block<Fr>,
+    /// what `witness` was built from
+    pub inputs: WitnessInputs,
+}
+
+impl CallWitness {
+    /// Rebuilds the witness of the transaction `tx_hash` from its inputs,
+    /// without any backend
+    pub fn from_inputs(
+        inputs: WitnessInputs,
+        tx_hash: H256,
+        circuits_params: CircuitsParams,
+    ) -> Result<Self, Error> {
+        let index = inputs
+            .block
+            .transactions
+            .iter()
+            .position(|tx| tx.hash == tx_hash)
+            .ok_or_else(|| {
+                Error::witness("transaction not in the witness block").with_tx(tx_hash)
+            })?;
+        let tx = inputs.block.transactions[index].clone();
+        let trace = inputs
+            .traces
+            .get(index)
+            .cloned()
+            .ok_or_else(|| Error::witness("missing transaction trace").with_tx(tx_hash))?;
+        let witness = inputs.witness(circuits_params)?;
+        Ok(Self {
+            tx,
+            trace,
+            witness,
+            inputs,
+        })
+    }
+
+    pub fn return_data(&self) -> Result<Bytes, Error> {
+        Bytes::from_str(&self.trace.return_value)
+            .map_err(|_| Error::rpc("invalid return data in trace").with_tx(self.tx.hash))
+    }
+
+    /// Whether the call itself ran out of gas, a call it makes running out
+    /// of gas is up to the caller to handle
+    pub fn is_out_of_gas(&self) -> bool {
+        self.trace.failed
+            && self
+                .trace
+                .struct_logs
+                .iter()
+                .rev()
+                .find(|step| step.depth == 1)
+                .and_then(|step| step.error.as_deref())
+                .map_or(false, |error| {
+                    // "out of gas" from geth, "OutOfGas" from revm
+                    error.to_lowercase().replace(' ', "").contains("outofgas")
+                })
+    }
+
+    /// Fails for calls that ran out of gas, which should be retried with a
+    /// higher limit rather than proven
+    pub fn check_gas(&self) -> Result<(), Error> {
+        if self.is_out_of_gas() {
+            return Err(Error::witness(format!(
+                "call ran out of gas with a limit of {}, pass a higher gas limit",
+                self.tx.gas
+            ))
+            .with_tx(self.tx.hash));
+        }
+        Ok(())
+    }
+
+    pub fn success(&self) -> bool {
+        !self.trace.failed
+    }
+
+    /// Decoded revert data, `None` if the call succeeded
+    pub fn revert_reason(&self) -> Result<Option<RevertReason>, Error> {
+        if self.success() {
+            return Ok(None);
+        }
+        Ok(Some(RevertReason::decode(&self.return_data()?)))
+    }
+
+    /// Fails for calls that reverted unless reverts are allowed on purpose
+    pub fn check_revert(&self, on_revert: OnRevert) -> Result<(), Error> {
+        match (on_revert, self.revert_reason()?) {
+            (OnRevert::Refuse, Some(reason)) => {
+                Err(Error::witness(format!("call reverted with {reason}")).with_tx(self.tx.hash))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Margin added on top of `eth_estimateGas`, in percent
+pub const GAS_MARGIN_PERCENT: u64 = 20;
+
+/// Options for the default struct log tracer as needed by the circuit input builder
+pub fn struct_log_tracing_options() -> GethDebugTracingOptions {
+    GethDebugTracingOptions {
+        enable_memory: Some(false),
+        disable_stack: Some(false),
+        disable_storage: Some(false),
+        enable_return_data: Some(true),
+        tracer: None,
+        tracer_config: None,
+        timeout: None,
+    }
+}
+
+/// State touched during execution but missing from an access set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MissingAccess {
+    Account(Address),
+    Storage(Address, Word),
+}
+
+/// Cross-checks an access set against the accounts and storage slots a
+/// `prestateTracer` reports as touched, any state missing from the access set
+/// would be missing from the witness.
+pub fn check_state_accesses(
+    access_set: &AccessSet,
+    prestate: &PrestateTrace,
+) -> Vec<MissingAccess> {
+    let mut missing = Vec::new();
+    for (address, account) in &prestate.0 {
+        match access_set.state.get(address) {
+            Some(keys) => {
+                for key in account.storage.keys() {
+                    let key = h256_to_u256(*key);
+                    if !keys.contains(&key) {
+                        missing.push(MissingAccess::Storage(*address, key));
+                    }
+                }
+            }
+            None => missing.push(MissingAccess::Account(*address)),
+        }
+    }
+    missing
+}
+
+#[cfg(feature = "anvil")]
+impl BuilderClient<AnvilClient> {
+    /// Gas limit for a call on top of the latest block, `eth_estimateGas`
+    /// plus `GAS_MARGIN_PERCENT` and capped at the block gas limit. Calls
+    /// that revert cannot be estimated and get the block gas limit so that
+    /// the revert can still be traced, any other estimation error is
+    /// returned.
+    pub async fn estimate_gas(
+        &self,
+        from: Address,
+        to: Option<Address>,
+        data: &Bytes,
+        value: Option<Word>,
+    ) -> Result<Word, Error> {
+        let block_number = self.backend.block_number()?;
+        let block_gas_limit = self
+            .backend
+            .block_by_number(block_number)
+            .await?
+            .ok_or_else(|| Error::rpc("block not found").with_block(block_number))?
+            .gas_limit;
+        let request = anvil_types::EthTransactionRequest {
+            from: Some(from.to_anvil_type()),
+            to: to.map(|to| to.to_anvil_type()),
+            gas_price: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            gas: None,
+            value: value.map(|value| value.to_anvil_type()),
+            data: Some(data.to_anvil_type()),
+            nonce: None,
+            chain_id: None,
+            access_list: None,
+            transaction_type: None,
+        };
+        match self
+            .backend
+            .estimate_gas(request.clone(), Some(block_number))
+            .await
+        {
+            Ok(estimate) => {
+                let gas = estimate + estimate * GAS_MARGIN_PERCENT / 100;
+                debug!(%estimate, %gas, "estimated gas");
+                Ok(gas.min(block_gas_limit))
+            }
+            Err(err) => {
+                let request = anvil_types::EthTransactionRequest {
+                    gas: Some(block_gas_limit.to_anvil_type()),
+                    ..request
+                };
+                let trace = self
+                    .backend
+                    .debug_trace_call(
+                        request,
+                        Some(block_number),
+                        GethDebugTracingOptions {
+                            disable_stack: Some(true),
+                            disable_storage: Some(true),
+                            ..struct_log_tracing_options()
+                        },
+                    )
+                    .await?;
+                if !trace.failed {
+                    return Err(err);
+                }
+                warn!(%err, "call reverts, using the block gas limit");
+                Ok(block_gas_limit)
+            }
+        }
+    }
+
+    /// Tops up the balance of `address` to at least `amount` with a balance
+    /// override, so the funded balance is assumed rather than proven
+    pub async fn fund(&mut self, address: Address, amount: Word) -> Result<(), Error> {
+        let balance = self.backend.get_balance(address, None).await?;
+        if balance >= amount {
+            return Ok(());
+        }
+        debug!(?address, %balance, %amount, "funding caller");
+        let account = AccountOverride {
+            balance: Some(amount),
+            ..self
+                .state_override
+                .0
+                .get(&address)
+                .cloned()
+                .unwrap_or_default()
+        };
+        self.apply_state_override(StateOverride([(address, account)].into()))
+            .await
+    }
+
+    /// Legacy gas price for a transaction in the next block, the latest
+    /// basefee plus the most it can rise in one block
+    pub async fn gas_price(&self) -> Result<Word, Error> {
+        let block_number = self.backend.block_number()?;
+        let base_fee = self
+            .backend
+            .block_by_number(block_number)
+            .await?
+            .ok_or_else(|| Error::rpc("block not found").with_block(block_number))?
+            .base_fee_per_gas
+            .unwrap_or_default();
+        Ok(base_fee + (base_fee + 7) / 8)
+    }
+
     pub async fn default() -> Result<Self, Error> {
         Self::from_circuits_params(CircuitsParams::default()).await
     }
@@ -63,34 +433,254 @@ impl BuilderClient {
         eth_rpc_url: Option<String>,
         fork_block_number: Option<usize>,
     ) -> Result<Self, Error> {
-        let anvil = AnvilClient::setup(eth_rpc_url.clone(), fork_block_number).await;
-        Self::new(anvil, circuits_params, eth_rpc_url, fork_block_number)
+        Self::from_anvil_config(
+            circuits_params,
+            AnvilClientConfig::default()
+                .with_eth_rpc_url(eth_rpc_url)
+                .with_fork_block_number(fork_block_number),
+        )
+        .await
+    }
+
+    /// Same as `from_config`, with every setting of the embedded anvil node.
+    /// A fork at the latest block records the block it was made at, as
+    /// storage can only be proven up to the fork block.
+    pub async fn from_anvil_config(
+        circuits_params: CircuitsParams,
+        anvil_config: AnvilClientConfig,
+    ) -> Result<Self, Error> {
+        let anvil = AnvilClient::setup_with_config(&anvil_config).await;
+        let fork_block_number = match (&anvil_config.eth_rpc_url, anvil_config.fork_block_number) {
+            (Some(_), None) => Some(anvil.block_number()?),
+            (_, fork_block_number) => fork_block_number,
+        };
+        Self::new(
+            anvil,
+            circuits_params,
+            anvil_config.eth_rpc_url,
+            fork_block_number,
+        )
+        .await
     }
 
     pub async fn from_circuits_params(circuits_params: CircuitsParams) -> Result<Self, Error> {
-        let anvil = AnvilClient::default().await;
-        Self::new(anvil, circuits_params, None, None)
+        let anvil = AnvilClient::default().await?;
+        Self::new(anvil, circuits_params, None, None).await
+    }
+
+    /// Applies the override set on top of the latest block without mining it,
+    /// so that the call still runs on top of the block its storage is proven at.
+    pub async fn apply_state_override(
+        &mut self,
+        state_override: StateOverride,
+    ) -> Result<(), Error> {
+        self.backend.apply_state_override(&state_override).await?;
+        self.state_override.0.extend(state_override.0);
+        Ok(())
+    }
+
+    /// Re-forks at another block of the same chain, dropping overrides and
+    /// any other local state.
+    pub async fn reset_fork(&mut self, fork_block_number: usize) -> Result<(), Error> {
+        self.backend
+            .reset_fork(self.eth_rpc_url.clone(), Some(fork_block_number))
+            .await?;
+        self.fork_block_number = Some(fork_block_number);
+        self.state_override = StateOverride::default();
+        Ok(())
+    }
+
+    /// Executes the raw transaction and generates the witness for the block it
+    /// is mined in. Everything runs inside a snapshot which is reverted
+    /// afterwards, so one fork can be reused to prove many independent calls.
+    pub async fn gen_witness_for_raw_transaction(
+        &self,
+        raw_tx: Bytes,
+    ) -> Result<CallWitness, Error> {
+        let snapshot_id = self.backend.snapshot().await?;
+        let result = self
+            .gen_witness_for_raw_transaction_unreverted(raw_tx)
+            .await;
+        self.backend.revert_after(snapshot_id, result).await
     }
 
-    pub fn new(
-        anvil: AnvilClient,
+    /// Traces the call with `debug_traceCall` and generates the witness for a
+    /// synthetic block holding just that call on top of the latest block. As
+    /// with `eth_call` nothing is mined, but the call pays for gas like a
+    /// transaction, so the caller needs a balance for the value and for `gas`
+    /// at `gas_price`. The synthetic block keeps the basefee of
+    /// the latest block. The circuit checks the signature
+    /// of every transaction, so the call is signed with `wallet` and its
+    /// caller has to be the wallet's address.
+    pub async fn gen_witness_for_call(
+        &self,
+        call: &EthCall,
+        wallet: &LocalWallet,
+    ) -> Result<CallWitness, Error> {
+        let signer: Address = wallet.address().to_zkevm_type();
+        if call.from != signer {
+            return Err(Error::config(format!(
+                "cannot sign a call from {:?} with the key of {signer:?}",
+                call.from
+            )));
+        }
+        let parent_number = self.backend.block_number()?;
+        let parent = self
+            .backend
+            .block_by_number(parent_number)
+            .await?
+            .ok_or_else(|| Error::rpc("parent block not found").with_block(parent_number))?;
+        let nonce = self.backend.get_nonce(call.from, None).await?;
+
+        let anvil_nonce: anvil_types::U256 = nonce.to_anvil_type();
+        let anvil_gas: anvil_types::U256 = call.gas.to_anvil_type();
+        let anvil_gas_price: anvil_types::U256 = call.gas_price.to_anvil_type();
+        let mut request = TransactionRequest::new()
+            .from(call.from.to_anvil_type())
+            .data(call.data.to_anvil_type())
+            .value(call.value.to_anvil_type())
+            .nonce(anvil_nonce)
+            .gas(anvil_gas)
+            .gas_price(anvil_gas_price)
+            .chain_id(self.chain_id.as_u64());
+        if let Some(to) = call.to {
+            request = request.to(to.to_anvil_type());
+        }
+        let trace = self
+            .backend
+            .debug_trace_call(
+                anvil_types::EthTransactionRequest {
+                    from: request.from,
+                    to: call.to.map(|to| to.to_anvil_type()),
+                    gas_price: request.gas_price,
+                    max_fee_per_gas: None,
+                    max_priority_fee_per_gas: None,
+                    gas: request.gas,
+                    value: request.value,
+                    data: request.data.clone(),
+                    nonce: request.nonce,
+                    chain_id: None,
+                    access_list: None,
+                    transaction_type: None,
+                },
+                Some(parent_number),
+                struct_log_tracing_options(),
+            )
+            .instrument(info_span!(
+                "tx_execution",
+                from = ?call.from,
+                to = ?call.to,
+                block_number = parent_number
+            ))
+            .await?;
+
+        let signature = wallet
+            .sign_transaction(&TypedTransaction::Legacy(request.clone()))
+            .await
+            .map_err(|err| Error::config(format!("cannot sign call: {err}")))?;
+        let raw_tx = request.rlp_signed(&signature);
+
+        let block_number = U64::from(parent_number + 1);
+        let tx = Transaction {
+            hash: H256::from(keccak256(&raw_tx)),
+            nonce,
+            block_number: Some(block_number),
+            transaction_index: Some(U64::zero()),
+            from: call.from,
+            to: call.to,
+            gas_price: Some(call.gas_price),
+            gas: call.gas,
+            value: call.value,
+            input: call.data.clone(),
+            v: U64::from(signature.v),
+            r: signature.r.to_zkevm_type(),
+            s: signature.s.to_zkevm_type(),
+            transaction_type: Some(U64::zero()),
+            chain_id: Some(self.chain_id),
+            ..Default::default()
+        };
+        let block = EthBlockFull {
+            parent_hash: parent
+                .hash
+                .ok_or_else(|| Error::rpc("block without hash").with_block(parent_number))?,
+            author: parent.author,
+            number: Some(block_number),
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 1,
+            difficulty: parent.difficulty,
+            mix_hash: parent.mix_hash,
+            base_fee_per_gas: Some(parent.base_fee_per_gas.unwrap_or_default()),
+            transactions: vec![tx.clone()],
+            ..Default::default()
+        };
+        let inputs = self.gen_witness_inputs(block, vec![trace.clone()]).await?;
+        let witness = inputs.witness(self.circuits_params)?;
+        Ok(CallWitness {
+            tx,
+            trace,
+            witness,
+            inputs,
+        })
+    }
+
+    async fn gen_witness_for_raw_transaction_unreverted(
+        &self,
+        raw_tx: Bytes,
+    ) -> Result<CallWitness, Error> {
+        let hash = async {
+            let hash = self.backend.send_raw_transaction(raw_tx).await?;
+            self.backend
+                .wait_for_transaction(hash, WaitOptions::default().with_auto_mine(true))
+                .await?;
+            Ok::<_, Error>(hash)
+        }
+        .instrument(info_span!("tx_execution"))
+        .await?;
+        let tx = self
+            .backend
+            .transaction_by_hash(hash)
+            .await?
+            .ok_or_else(|| Error::rpc("transaction not found").with_tx(hash))?;
+        let block_number = tx
+            .block_number
+            .ok_or_else(|| Error::rpc("transaction not mined").with_tx(hash))?;
+        let (block, traces) = self.get_block_traces(block_number.as_usize()).await?;
+        let trace = tx
+            .transaction_index
+            .and_then(|index| traces.get(index.as_usize()))
+            .cloned()
+            .ok_or_else(|| {
+                Error::rpc("transaction trace not found")
+                    .with_block(block_number.as_u64())
+                    .with_tx(hash)
+            })?;
+        let inputs = self.gen_witness_inputs(block, traces).await?;
+        let witness = inputs.witness(self.circuits_params)?;
+        Ok(CallWitness {
+            tx,
+            trace,
+            witness,
+            inputs,
+        })
+    }
+}
+
+impl<B: ChainBackend> BuilderClient<B> {
+    pub async fn new(
+        backend: B,
         circuits_params: CircuitsParams,
         eth_rpc_url: Option<String>,
         fork_block_number: Option<usize>,
     ) -> Result<Self, Error> {
-        if let Some(chain_id) = anvil.eth_chain_id()? {
-            Ok(Self {
-                anvil,
-                chain_id: Word::from(chain_id.as_usize()),
-                circuits_params,
-                eth_rpc_url,
-                fork_block_number,
-            })
-        } else {
-            Err(Error::InternalError(
-                "Unable to get chain id from ETH client",
-            ))
-        }
+        let chain_id = backend.chain_id().await?;
+        Ok(Self {
+            backend,
+            chain_id,
+            circuits_params,
+            eth_rpc_url,
+            fork_block_number,
+            state_override: StateOverride::default(),
+        })
     }
 
     pub async fn gen_witness(
@@ -98,109 +688,198 @@ impl BuilderClient {
         block_number: usize,
     ) -> Result<zkevm_circuits::witness::Block<Fr>, Error> {
         let (circuit_input_builder, _) = self.gen_inputs(block_number).await?;
-        Ok(block_convert::<Fr>(
-            &circuit_input_builder.block,
-            &circuit_input_builder.code_db,
-        )?)
+        info_span!("witness_conversion").in_scope(|| {
+            Ok(block_convert::<Fr>(
+                &circuit_input_builder.block,
+                &circuit_input_builder.code_db,
+            )?)
+        })
+    }
+
+    pub async fn gen_witness_for_block(
+        &self,
+        block: EthBlockFull,
+        traces: Vec<GethExecTrace>,
+    ) -> Result<zkevm_circuits::witness::Block<Fr>, Error> {
+        self.gen_witness_inputs(block, traces)
+            .await?
+            .witness(self.circuits_params)
     }
 
     pub async fn gen_inputs(
         &self,
         block_number: usize,
     ) -> Result<(CircuitInputBuilder, EthBlockFull), Error> {
-        let (mut block, traces, history_hashes, prev_state_root) =
-            self.get_block(block_number).await?;
+        let (block, traces) = self.get_block_traces(block_number).await?;
+        self.gen_inputs_for_block(block, traces).await
+    }
+
+    pub async fn gen_inputs_for_block(
+        &self,
+        block: EthBlockFull,
+        traces: Vec<GethExecTrace>,
+    ) -> Result<(CircuitInputBuilder, EthBlockFull), Error> {
+        let inputs = self.gen_witness_inputs(block, traces).await?;
+        Ok((
+            inputs.circuit_input_builder(self.circuits_params)?,
+            inputs.block,
+        ))
+    }
+
+    /// Fetches the inputs for a block which need not exist on the backend,
+    /// only its parent does, e.g. a block built around a traced call.
+    pub async fn gen_witness_inputs(
+        &self,
+        mut block: EthBlockFull,
+        traces: Vec<GethExecTrace>,
+    ) -> Result<WitnessInputs, Error> {
+        let block_number = block
+            .number
+            .ok_or_else(|| Error::witness("block without number"))?
+            .as_usize();
+        let parent_number = parent_block_number(block_number)?;
+        let (history_hashes, prev_state_root) = self.get_history(block_number).await?;
         let access_set: AccessSet = get_state_accesses(&block, &traces)?;
         let (proofs, codes, new_state_root) =
             self.get_state(block_number, access_set.clone()).await?;
         if block.state_root.is_zero() {
             block.state_root = new_state_root;
         }
-        let (state_db, code_db) = build_state_code_db(proofs, codes);
-        let builder = self
-            .gen_inputs_from_state(
-                state_db,
-                code_db,
-                &block,
-                &traces,
-                history_hashes,
-                prev_state_root,
-                self.gen_axiom_inputs(access_set).await,
-            )
-            .await?;
-        Ok((builder, block))
+        Ok(WitnessInputs {
+            chain_id: self.chain_id,
+            axiom_inputs: self.gen_axiom_inputs(access_set, parent_number).await?,
+            block,
+            traces,
+            history_hashes,
+            prev_state_root,
+            proofs,
+            codes: codes
+                .into_iter()
+                .map(|(address, code)| (address, code.into()))
+                .collect(),
+        })
     }
 
-    async fn gen_axiom_inputs(&self, access_set: AccessSet) -> EthBlockStorageInput {
-        println!("access_set {:?}", access_set);
-        // currently only proving one account
-        let vec = access_set.state.iter().collect_vec();
-        let mut first_account_storage_list = vec[0];
-        for el in vec {
+    /// Fetches the storage proofs for axiom at `block_number`, the parent of
+    /// the proven block. A fork's storage is proven on the remote rpc, which
+    /// only knows the blocks up to the fork block, without one the backend's
+    /// own chain is used.
+    #[instrument(name = "axiom_inputs", skip_all, fields(block_number = block_number))]
+    async fn gen_axiom_inputs(
+        &self,
+        access_set: AccessSet,
+        block_number: usize,
+    ) -> Result<EthBlockStorageInput, Error> {
+        debug!(
+            accounts = access_set.state.len(),
+            codes = access_set.code.len(),
+            "access set"
+        );
+        let eth_rpc_url = match &self.eth_rpc_url {
+            Some(eth_rpc_url) => {
+                if let Some(fork_block_number) = self.fork_block_number {
+                    if block_number > fork_block_number {
+                        return Err(Error::witness(format!(
+                            "storage can only be proven up to the fork block {fork_block_number}"
+                        ))
+                        .with_block(block_number));
+                    }
+                }
+                eth_rpc_url.clone()
+            }
+            None => self
+                .backend
+                .rpc_url()
+                .ok_or_else(|| Error::config("an rpc url is needed to prove storage with axiom"))?,
+        };
+        let axiom_block_number = u32::try_from(block_number).map_err(|_| {
+            Error::witness("block number out of range for axiom").with_block(block_number)
+        })?;
+        let provider = Provider::new_client(&eth_rpc_url, 10, 500)
+            .map_err(|err| Error::config(format!("invalid rpc url {eth_rpc_url}: {err}")))?;
+        // currently only proving one account, overridden slots are assumed rather
+        // than proven so they are left out of the storage proven at the parent
+        // block. the rest of an overridden account is still proven there.
+        let vec = access_set
+            .state
+            .iter()
+            .map(|(address, keys)| {
+                let keys = keys
+                    .iter()
+                    .filter(|key| !self.state_override.is_slot_overridden(address, key))
+                    .collect_vec();
+                (address, keys)
+            })
+            .collect_vec();
+        let mut first_account_storage_list = vec.first().ok_or_else(|| {
+            Error::witness("no account to prove storage of").with_block(block_number)
+        })?;
+        for el in &vec {
             if !el.1.is_empty() {
                 first_account_storage_list = el;
                 break;
             }
         }
 
-        get_block_storage_input_async(
-            &Provider::new_client(self.eth_rpc_url.clone().unwrap().as_str(), 10, 500).unwrap(),
-            self.fork_block_number.unwrap() as u32,
+        // axiom unwraps the responses of the rpc, a failing request panics
+        AssertUnwindSafe(get_block_storage_input_async(
+            &provider,
+            axiom_block_number,
             *first_account_storage_list.0,
             first_account_storage_list
                 .1
                 .iter()
-                .map(H256::from_uint)
+                .map(|key| H256::from_uint(*key))
                 .collect_vec(),
             10,
             10,
-        )
+        ))
+        .catch_unwind()
         .await
+        .map_err(|panic| {
+            let message = panic
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| panic.downcast_ref::<&str>().copied())
+                .unwrap_or("unknown error");
+            Error::rpc(format!("cannot fetch storage proofs for axiom: {message}"))
+                .with_block(block_number)
+        })
     }
 
-    #[allow(clippy::too_many_arguments)]
-    async fn gen_inputs_from_state(
-        &self,
-        sdb: StateDB,
-        code_db: CodeDB,
-        eth_block: &EthBlockFull,
-        geth_traces: &[GethExecTrace],
-        history_hashes: Vec<Word>,
-        prev_state_root: Word,
-        axiom_inputs: EthBlockStorageInput,
-    ) -> Result<CircuitInputBuilder, Error> {
-        let block = Block::new(
-            self.chain_id,
-            history_hashes,
-            prev_state_root,
-            eth_block,
-            self.circuits_params,
-            axiom_inputs,
-        )?;
-        let mut builder = CircuitInputBuilder::new(sdb, code_db, block);
-        builder.handle_block(eth_block, geth_traces)?;
-        Ok(builder)
-    }
-
-    async fn get_block(
+    pub async fn cross_check_state_accesses(
         &self,
         block_number: usize,
-    ) -> Result<(EthBlockFull, Vec<GethExecTrace>, Vec<Word>, Word), Error> {
+    ) -> Result<Vec<MissingAccess>, Error> {
         let (block, traces) = self.get_block_traces(block_number).await?;
+        let access_set = get_state_accesses(&block, &traces)?;
+        let mut missing = Vec::new();
+        for tx in &block.transactions {
+            let prestate = self
+                .backend
+                .debug_trace_transaction_prestate(tx.hash)
+                .await?;
+            missing.extend(check_state_accesses(&access_set, &prestate));
+        }
+        Ok(missing)
+    }
 
+    async fn get_history(&self, block_number: usize) -> Result<(Vec<Word>, Word), Error> {
         // fetch up to 256 blocks
         let n_blocks = std::cmp::min(256, block_number);
         let mut futures = Vec::default();
         for i in 1..n_blocks {
-            let header_future = self.anvil.block_by_number(block_number - i);
+            let header_future = self.backend.block_by_number(block_number - i);
             futures.push(header_future);
         }
 
         let mut prev_state_root: Option<Word> = None;
         let mut history_hashes = Vec::default();
         let results = future::join_all(futures).await;
-        for result in results {
-            let header = result?.expect("parent block not found");
+        for (index, result) in results.into_iter().enumerate() {
+            let header = result?.ok_or_else(|| {
+                Error::rpc("history block not found").with_block(block_number - 1 - index)
+            })?;
 
             // set the previous state root
             if prev_state_root.is_none() {
@@ -208,79 +887,69 @@ impl BuilderClient {
             }
 
             // latest block hash is the last item
-            let block_hash = header
-                .hash
-                .ok_or(Error::InternalError("Incomplete block"))?;
+            let block_hash = header.hash.ok_or_else(|| {
+                Error::rpc("block without hash").with_block(block_number - 1 - index)
+            })?;
             history_hashes.push(h256_to_u256(block_hash));
         }
 
-        Ok((
-            block,
-            traces,
-            history_hashes,
-            prev_state_root.unwrap_or_default(),
-        ))
+        Ok((history_hashes, prev_state_root.unwrap_or_default()))
     }
 
+    #[instrument(name = "trace_fetch", skip(self))]
     async fn get_block_traces(
         &self,
         block_number: usize,
     ) -> Result<(EthBlockFull, Vec<GethExecTrace>), Error> {
         let block = self
-            .anvil
+            .backend
             .block_by_number_full(block_number)
             .await?
-            .expect("block not found");
+            .ok_or_else(|| Error::rpc("block not found").with_block(block_number))?;
 
         let mut traces = Vec::default();
         for tx in &block.transactions {
             let anvil_trace = self
-                .anvil
-                .debug_trace_transaction(
-                    tx.hash,
-                    GethDebugTracingOptions {
-                        enable_memory: Some(false),
-                        disable_stack: Some(false),
-                        disable_storage: Some(false),
-                        enable_return_data: Some(true),
-                        tracer: None,
-                        tracer_config: None,
-                        timeout: None,
-                    },
-                )
+                .backend
+                .debug_trace_transaction(tx.hash, struct_log_tracing_options())
                 .await?;
 
-            println!("returndata: {}", anvil_trace.return_value);
-            println!("gas used: {:?}", anvil_trace.gas);
+            debug!(
+                tx_hash = ?tx.hash,
+                gas_used = ?anvil_trace.gas,
+                failed = anvil_trace.failed,
+                return_value = %anvil_trace.return_value,
+                "traced transaction"
+            );
             traces.push(anvil_trace);
         }
-        // println!("traces: {:#?}", traces);
         Ok((block, traces))
     }
 
+    #[instrument(
+        name = "state_fetch",
+        skip_all,
+        fields(block_number = block_number, accounts = access_set.state.len())
+    )]
     async fn get_state(
         &self,
         block_number: usize,
         access_set: AccessSet,
     ) -> Result<(Vec<EIP1186ProofResponse>, HashMap<Address, Vec<u8>>, H256), Error> {
+        let parent_number = parent_block_number(block_number)?;
         let mut proofs = Vec::default();
         for (address, key_set) in access_set.state.clone() {
             let mut keys: Vec<Word> = key_set.iter().cloned().collect();
             keys.sort();
             let proof = self
-                .anvil
-                .get_proof(address, keys, Some(block_number - 1))
-                .await
-                .unwrap();
+                .backend
+                .get_proof(address, keys, Some(parent_number))
+                .await?;
             proofs.push(proof);
         }
         let mut codes: HashMap<Address, Vec<u8>> = HashMap::default();
         for address in access_set.code.clone() {
-            let code = self
-                .anvil
-                .get_code(address, Some(block_number - 1))
-                .await
-                .unwrap();
+            let code = self.backend.get_code(address, Some(parent_number)).await?;
             codes.insert(address, code.to_vec());
         }
 
@@ -347,18 +1016,97 @@ impl BuilderClient {
 
 #[cfg(test)]
 mod tests {
-    use crate::anvil::AnvilClient;
-    use crate::inputs_builder::BuilderClient;
-    use bus_mapping::circuit_input_builder::CircuitsParams;
+    #[cfg(feature = "anvil")]
+    use crate::anvil::{AnvilClient, WaitOptions};
+    use crate::backend::MemoryBackend;
+    #[cfg(feature = "anvil")]
+    use crate::env::Env;
+    use crate::inputs_builder::{check_state_accesses, BuilderClient, MissingAccess};
+    use crate::trace::{PrestateAccount, PrestateTrace};
+    use crate::types::zkevm_types::{u256_to_h256, Address, EthBlockFull, Word, H256, U64};
+    use bus_mapping::{
+        circuit_input_builder::{Access, AccessSet, AccessValue, CircuitsParams},
+        operation::RW,
+    };
+
+    #[test]
+    fn test_check_state_accesses() {
+        let address = Address::repeat_byte(1);
+        let access_set = AccessSet::from(vec![Access::new(
+            None,
+            RW::READ,
+            AccessValue::Storage {
+                address,
+                key: Word::from(1),
+            },
+        )]);
+
+        let mut account = PrestateAccount::default();
+        account
+            .storage
+            .insert(u256_to_h256(Word::from(1)), Default::default());
+        let mut prestate = PrestateTrace::default();
+        prestate.0.insert(address, account.clone());
+        assert!(check_state_accesses(&access_set, &prestate).is_empty());
+
+        account
+            .storage
+            .insert(u256_to_h256(Word::from(2)), Default::default());
+        prestate.0.insert(address, account);
+        prestate
+            .0
+            .insert(Address::repeat_byte(2), Default::default());
+        assert_eq!(
+            check_state_accesses(&access_set, &prestate),
+            vec![
+                MissingAccess::Storage(address, Word::from(2)),
+                MissingAccess::Account(Address::repeat_byte(2)),
+            ]
+        );
+    }
 
+    #[tokio::test]
+    async fn test_memory_backend() {
+        let mut backend = MemoryBackend::new(Word::from(1));
+        for number in 0..3u64 {
+            backend
+                .insert_block(EthBlockFull {
+                    hash: Some(H256::from_low_u64_be(number + 1)),
+                    number: Some(U64::from(number)),
+                    state_root: H256::from_low_u64_be(number + 100),
+                    author: Some(Address::zero()),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let bc = BuilderClient::new(backend, CircuitsParams::default(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(bc.chain_id, Word::from(1));
+
+        let (block, traces) = bc.get_block_traces(2).await.unwrap();
+        assert_eq!(block.number, Some(U64::from(2)));
+        assert!(traces.is_empty());
+        let (history_hashes, prev_state_root) = bc.get_history(2).await.unwrap();
+        assert_eq!(history_hashes.len(), 1);
+        assert_eq!(prev_state_root, Word::from(101));
+
+        // the genesis block has no parent to fetch the state from
+        let err = bc.gen_inputs(0).await.unwrap_err();
+        assert!(err.to_string().contains("genesis"));
+    }
+
+    #[cfg(feature = "anvil")]
     #[tokio::test]
     async fn test() {
         let anvil = AnvilClient::setup(None, None).await;
-        let bc = BuilderClient::new(anvil, CircuitsParams::default(), None, None).unwrap();
+        let bc = BuilderClient::new(anvil, CircuitsParams::default(), None, None)
+            .await
+            .unwrap();
         assert_eq!(bc.chain_id.as_usize(), 31337);
 
         let hash = bc
-            .anvil
+            .backend
             .fund_wallet(
                 "0x2CA4c197AE776f675A114FBCB0B03Be845f0316d"
                     .parse()
@@ -367,21 +1115,56 @@ mod tests {
             .await
             .unwrap();
 
-        loop {
-            if let Some(tx) = bc.anvil.transaction_by_hash(hash).await.unwrap() {
-                if let Some(block_number) = tx.block_number {
-                    let (block, traces) =
-                        bc.get_block_traces(block_number.as_usize()).await.unwrap();
-                    assert_eq!(block.transactions.len(), 1);
-                    assert_eq!(traces.len(), 1);
-                    break;
-                } else {
-                    bc.anvil.mine_one().await;
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                }
-            } else {
-                panic!("transaction not available");
-            }
-        }
+        let rc = bc
+            .backend
+            .wait_for_transaction(hash, WaitOptions::default().with_auto_mine(true))
+            .await
+            .unwrap();
+        let block_number = rc.block_number.unwrap();
+        let (block, traces) = bc.get_block_traces(block_number.as_usize()).await.unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(traces.len(), 1);
+    }
+
+    #[cfg(feature = "anvil")]
+    #[tokio::test]
+    #[ignore = "needs ETH_RPC_URL archive node"]
+    async fn test_reset_fork() {
+        let eth_rpc_url = Env::load()
+            .unwrap()
+            .eth_rpc_url
+            .expect("ETH_RPC_URL is not set");
+        let mut bc = BuilderClient::from_config(CircuitsParams::default(), Some(eth_rpc_url), None)
+            .await
+            .unwrap();
+        let fork_block_number = bc.backend.block_number().unwrap();
+        let address = Address::repeat_byte(0x33);
+        let balance = bc.backend.get_balance(address, None).await.unwrap();
+
+        bc.backend
+            .set_balance(address, balance + Word::from(1))
+            .await
+            .unwrap();
+        bc.backend.mine_one().await;
+        assert_eq!(bc.backend.block_number().unwrap(), fork_block_number + 1);
+
+        bc.reset_fork(fork_block_number - 1).await.unwrap();
+        assert_eq!(bc.fork_block_number, Some(fork_block_number - 1));
+        assert_eq!(bc.backend.block_number().unwrap(), fork_block_number - 1);
+        let previous_balance = bc.backend.get_balance(address, None).await.unwrap();
+
+        bc.reset_fork(fork_block_number).await.unwrap();
+        assert_eq!(bc.backend.block_number().unwrap(), fork_block_number);
+        assert_eq!(
+            bc.backend.get_balance(address, None).await.unwrap(),
+            balance
+        );
+        assert_eq!(
+            bc.backend
+                .get_balance(address, Some(fork_block_number - 1))
+                .await
+                .unwrap(),
+            previous_balance
+        );
     }
 }
@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{error::Error, prover::ProofBundle, types::zkevm_types::Bytes};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done { result: Bytes, proof: ProofBundle },
+    Failed { error: String },
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Pending | JobStatus::Running)
+    }
+}
+
+/// What `JobStore` writes to disk
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobsFile {
+    next_id: u64,
+    jobs: BTreeMap<u64, JobStatus>,
+}
+
+/// Store of proving jobs, cheap to clone and share across tasks. Kept in
+/// memory unless opened with `open`.
+#[derive(Clone, Debug, Default)]
+pub struct JobStore {
+    next_id: Arc<AtomicU64>,
+    jobs: Arc<Mutex<BTreeMap<u64, JobStatus>>>,
+    path: Option<PathBuf>,
+}
+
+impl JobStore {
+    /// Store persisted to `path` on every change. Jobs a previous process
+    /// left pending or running are marked failed, they will not be resumed.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let mut file = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => JobsFile::default(),
+            Err(err) => return Err(err.into()),
+        };
+        for status in file.jobs.values_mut() {
+            if !status.is_finished() {
+                *status = JobStatus::Failed {
+                    error: "interrupted by a restart".to_string(),
+                };
+            }
+        }
+        let store = Self {
+            next_id: Arc::new(AtomicU64::new(file.next_id)),
+            jobs: Arc::new(Mutex::new(file.jobs)),
+            path: Some(path),
+        };
+        store.persist(&store.lock());
+        Ok(store)
+    }
+
+    pub fn create(&self) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut jobs = self.lock();
+        jobs.insert(id, JobStatus::Pending);
+        self.persist(&jobs);
+        id
+    }
+
+    /// Sets the status of a job, cancelled jobs keep their status
+    pub fn update(&self, id: u64, status: JobStatus) {
+        let mut jobs = self.lock();
+        if matches!(jobs.get(&id), Some(JobStatus::Cancelled)) {
+            return;
+        }
+        jobs.insert(id, status);
+        self.persist(&jobs);
+    }
+
+    pub fn get(&self, id: u64) -> Option<JobStatus> {
+        self.lock().get(&id).cloned()
+    }
+
+    /// Cancels a pending or running job, `false` if it had already finished
+    pub fn cancel(&self, id: u64) -> Option<bool> {
+        let mut jobs = self.lock();
+        let status = jobs.get_mut(&id)?;
+        if status.is_finished() {
+            return Some(false);
+        }
+        *status = JobStatus::Cancelled;
+        self.persist(&jobs);
+        Some(true)
+    }
+
+    /// The jobs map stays consistent even if a thread panicked while holding
+    /// the lock, so a poisoned lock is recovered instead of failing every
+    /// later call
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u64, JobStatus>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn persist(&self, jobs: &BTreeMap<u64, JobStatus>) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let file = JobsFile {
+            next_id: self.next_id.load(Ordering::SeqCst),
+            jobs: jobs.clone(),
+        };
+        // write to a temporary file first so that a crash never leaves a
+        // truncated store behind
+        let tmp_path = path.with_extension("tmp");
+        let result = File::create(&tmp_path)
+            .map_err(Error::from)
+            .and_then(|tmp| Ok(serde_json::to_writer(tmp, &file)?))
+            .and_then(|_| Ok(fs::rename(&tmp_path, path)?));
+        if let Err(err) = result {
+            warn!(%err, path = %path.display(), "cannot persist jobs");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobStatus, JobStore};
+
+    #[test]
+    fn test_persist() {
+        let path =
+            std::env::temp_dir().join(format!("zk_eth_call_jobs_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let store = JobStore::open(path.clone()).unwrap();
+        let failed = store.create();
+        store.update(
+            failed,
+            JobStatus::Failed {
+                error: "reverted".to_string(),
+            },
+        );
+        let running = store.create();
+        store.update(running, JobStatus::Running);
+        let cancelled = store.create();
+        assert_eq!(store.cancel(cancelled), Some(true));
+        store.update(cancelled, JobStatus::Running);
+        assert_eq!(store.cancel(failed), Some(false));
+        assert_eq!(store.cancel(42), None);
+
+        let store = JobStore::open(path.clone()).unwrap();
+        assert!(
+            matches!(store.get(failed), Some(JobStatus::Failed { error }) if error == "reverted")
+        );
+        assert!(matches!(store.get(running), Some(JobStatus::Failed { .. })));
+        assert!(matches!(store.get(cancelled), Some(JobStatus::Cancelled)));
+        assert_eq!(store.create(), cancelled + 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_poisoned() {
+        let store = JobStore::default();
+        let id = store.create();
+        let jobs = store.clone();
+        std::thread::spawn(move || {
+            let _guard = jobs.jobs.lock().unwrap();
+            panic!("job handler panicked");
+        })
+        .join()
+        .unwrap_err();
+        assert!(store.jobs.is_poisoned());
+
+        store.update(id, JobStatus::Running);
+        assert!(matches!(store.get(id), Some(JobStatus::Running)));
+        assert_eq!(store.cancel(id), Some(true));
+    }
+}
@@ -1,7 +1,26 @@
+pub mod abi;
 pub mod anvil;
+pub mod backend;
+#[cfg(feature = "anvil")]
+pub mod batch;
+pub mod call;
+pub mod config;
+#[cfg(feature = "anvil")]
+pub mod daemon;
 pub mod env;
 pub mod error;
 pub mod inputs_builder;
+pub mod jobs;
+pub mod overrides;
+pub mod prover;
+pub mod public_inputs;
+pub mod revert;
+#[cfg(feature = "anvil")]
+pub mod rpc;
+pub mod telemetry;
+pub mod trace;
 pub mod types;
+pub mod verifier;
+pub mod witness;
 
 pub use inputs_builder::{BuilderClient, CircuitsParams};
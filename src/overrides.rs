@@ -0,0 +1,65 @@
+use std::{collections::BTreeMap, fs::File, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    types::zkevm_types::{h256_to_u256, Address, Bytes, Word, H256, U256, U64},
+};
+
+/// Overrides for a single account, same shape as in `eth_call`'s state override set
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// replaces the whole storage of the account, not supported on a fork
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<H256, H256>>,
+    /// replaces individual storage slots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<H256, H256>>,
+}
+
+/// State assumed by the prover on top of the forked chain. Unlike the rest of
+/// the state, none of this is proven against the block's state root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StateOverride(pub BTreeMap<Address, AccountOverride>);
+
+impl StateOverride {
+    pub fn from_json_file(path: &Path) -> Result<Self, Error> {
+        let state_override: StateOverride = serde_json::from_reader(File::open(path)?)?;
+        state_override.validate()?;
+        Ok(state_override)
+    }
+
+    /// Rejects overrides that cannot be applied on a fork
+    pub fn validate(&self) -> Result<(), Error> {
+        for account in self.0.values() {
+            if account.state.is_some() {
+                return Err(Error::config(
+                    "full storage override is not supported, use stateDiff",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_slot_overridden(&self, address: &Address, key: &Word) -> bool {
+        self.0
+            .get(address)
+            .and_then(|account| account.state_diff.as_ref())
+            .map_or(false, |state_diff| {
+                state_diff.keys().any(|slot| h256_to_u256(*slot) == *key)
+            })
+    }
+}
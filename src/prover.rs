@@ -0,0 +1,521 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use eth_types::Fr;
+use ethers_core::utils::hex;
+use halo2_proofs::{
+    dev::MockProver,
+    halo2curves::{
+        bn256::{Bn256, G1Affine},
+        group::ff::PrimeField,
+    },
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+    SerdeFormat,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng, ChaChaRng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+use zkevm_circuits::{super_circuit::SuperCircuit, util::SubCircuit};
+
+use crate::{
+    abi,
+    error::Error,
+    inputs_builder::{CallWitness, CircuitsParams},
+    overrides::StateOverride,
+    revert::RevertReason,
+    types::zkevm_types::{Address, Bytes, Word, H256, U64},
+};
+
+pub const MAX_TXS: usize = 1;
+pub const MAX_CALLDATA: usize = 256;
+pub const RANDOMNESS: u64 = 0x100;
+
+pub const DEFAULT_CIRCUITS_PARAMS: CircuitsParams = CircuitsParams {
+    max_rws: 1000,
+    max_txs: MAX_TXS,
+    max_calldata: MAX_CALLDATA,
+    max_copy_rows: 1000,
+    max_exp_steps: 1000,
+    max_bytecode: 512,
+    max_evm_rows: 1000,
+    max_keccak_rows: 10000,
+};
+
+pub type CallCircuit = SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA, RANDOMNESS>;
+
+/// Builds the circuit for a call witness, returns it with its public instance
+pub fn build_circuit(call_witness: &mut CallWitness) -> (CallCircuit, Vec<Vec<Fr>>) {
+    call_witness.witness.randomness = Fr::from(RANDOMNESS);
+    let circuit = CallCircuit::new_from_block(&call_witness.witness);
+    let instance = circuit.instance();
+    (circuit, instance)
+}
+
+/// Checks the circuit constraints with the `MockProver`, much faster than proving
+#[instrument(name = "verification", skip_all, fields(k = k, mock = true))]
+pub fn mock_verify(k: u32, circuit: &CallCircuit, instance: Vec<Vec<Fr>>) -> Result<(), Error> {
+    let prover = MockProver::run(k, circuit, instance)?;
+    prover.verify_par().map_err(|failures| {
+        Error::proof(format!(
+            "circuit constraints not satisfied, {} failures",
+            failures.len()
+        ))
+    })
+}
+
+/// Big-endian hex encoding of a field element
+pub fn fr_to_hex(value: &Fr) -> String {
+    let mut bytes = value.to_repr();
+    bytes.reverse();
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Parses the big-endian hex encoding of `fr_to_hex`
+pub fn fr_from_hex(value: &str) -> Result<Fr, Error> {
+    let invalid = || Error::conversion(format!("invalid field element {value}"));
+    let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|_| invalid())?;
+    if bytes.len() > 32 {
+        return Err(invalid());
+    }
+    let mut repr = [0u8; 32];
+    for (index, byte) in bytes.iter().rev().enumerate() {
+        repr[index] = *byte;
+    }
+    Option::from(Fr::from_repr(repr)).ok_or_else(invalid)
+}
+
+/// A proven call along with its public instance and proof
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofBundle {
+    pub chain_id: Word,
+    /// block the call was proven in
+    pub block_number: U64,
+    pub tx_hash: H256,
+    pub from: Address,
+    pub to: Option<Address>,
+    /// true for a contract creation, `to` is then `None`. The public inputs
+    /// encode the destination of a creation as the zero address, so only
+    /// this flag tells a creation from a call to the zero address.
+    #[serde(default)]
+    pub create: bool,
+    /// wei sent along with the call, not attested by the proof as it only
+    /// enters `rpi_rlc`, see `PublicInputs`
+    #[serde(default)]
+    pub value: Word,
+    pub calldata: Bytes,
+    /// false if the call reverted, `return_data` is then the revert data.
+    /// Read from the trace, the public inputs have no success flag so it is
+    /// not attested by the proof.
+    #[serde(default = "default_success")]
+    pub success: bool,
+    pub return_data: Bytes,
+    /// decoded revert data if the call reverted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<RevertReason>,
+    /// signature used to decode `return_data`, e.g. `balanceOf(address)(uint256)`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// return data decoded with `signature`, see `abi::token_to_json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_return: Option<serde_json::Value>,
+    pub k: u32,
+    pub instance: Vec<String>,
+    /// only set for real proofs, not for mock verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<Bytes>,
+    /// state assumed rather than proven, see `StateOverride`. Metadata only,
+    /// the proof does not commit to it.
+    #[serde(default, skip_serializing_if = "StateOverride::is_empty")]
+    pub assumed_state: StateOverride,
+}
+
+impl ProofBundle {
+    /// File name every bundle is written under, `bundle_<k>_<tx hash>.json`
+    pub fn file_name(&self) -> String {
+        format!("bundle_{}_{}.json", self.k, hex::encode(self.tx_hash))
+    }
+
+    /// Writes the bundle to `dir` under `file_name` and returns its path
+    pub fn write(&self, dir: &Path) -> Result<PathBuf, Error> {
+        let path = dir.join(self.file_name());
+        serde_json::to_writer_pretty(BufWriter::new(File::create(&path)?), self)?;
+        Ok(path)
+    }
+
+    pub fn new(
+        call_witness: &CallWitness,
+        chain_id: Word,
+        k: u32,
+        instance: &[Vec<Fr>],
+        assumed_state: StateOverride,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            chain_id,
+            block_number: call_witness.tx.block_number.ok_or_else(|| {
+                Error::witness("transaction not mined").with_tx(call_witness.tx.hash)
+            })?,
+            tx_hash: call_witness.tx.hash,
+            from: call_witness.tx.from,
+            to: call_witness.tx.to,
+            create: call_witness.tx.to.is_none(),
+            value: call_witness.tx.value,
+            calldata: call_witness.tx.input.clone(),
+            success: call_witness.success(),
+            return_data: call_witness.return_data()?,
+            revert_reason: call_witness.revert_reason()?,
+            signature: None,
+            decoded_return: None,
+            k,
+            instance: instance[0].iter().map(fr_to_hex).collect(),
+            proof: None,
+            assumed_state,
+        })
+    }
+
+    /// Decodes the return data with the signature's return types, the revert
+    /// data of a reverted call is left to `revert_reason`
+    pub fn decode_return(&mut self, signature: &str) -> Result<(), Error> {
+        self.signature = Some(signature.to_string());
+        if !self.success {
+            return Ok(());
+        }
+        let function = abi::parse_signature(signature)?;
+        let tokens = abi::decode_output(&function, &self.return_data)?;
+        self.decoded_return = Some(abi::tokens_to_json(&tokens));
+        Ok(())
+    }
+}
+
+fn default_success() -> bool {
+    true
+}
+
+fn not_set_up(what: &str) -> Error {
+    Error::proof(format!("{what} not set up"))
+}
+
+fn missing_file(path: &Path) -> Error {
+    Error::proof(format!("{} not found, run a setup first", path.display()))
+}
+
+pub struct RealProver {
+    pub degree: u32,
+    dir_path: PathBuf,
+    serde_format: SerdeFormat,
+    rng: Option<ChaCha20Rng>,
+    general_params: Option<ParamsKZG<Bn256>>,
+    verifier_params: Option<ParamsKZG<Bn256>>,
+    circuit_proving_key: Option<ProvingKey<G1Affine>>,
+    circuit_verifying_key: Option<VerifyingKey<G1Affine>>,
+}
+
+impl RealProver {
+    pub fn init(degree: u32, dir_path: PathBuf) -> Self {
+        Self {
+            degree,
+            dir_path,
+            serde_format: SerdeFormat::RawBytes,
+            rng: None,
+            general_params: None,
+            verifier_params: None,
+            circuit_proving_key: None,
+            circuit_verifying_key: None,
+        }
+    }
+
+    pub fn with_serde_format(mut self, serde_format: SerdeFormat) -> Self {
+        self.serde_format = serde_format;
+        self
+    }
+
+    #[instrument(name = "keygen", skip_all, fields(k = self.degree))]
+    pub fn setup_global(&mut self) -> Result<(), Error> {
+        self.setup_general_params()?;
+        self.setup_verifier_params()?;
+        Ok(())
+    }
+
+    #[instrument(name = "proving", skip_all, fields(k = self.degree))]
+    pub fn prove(&self, circuit: CallCircuit, instance: Vec<Vec<Fr>>) -> Result<Vec<u8>, Error> {
+        let instance_refs: Vec<&[Fr]> = instance.iter().map(|v| &v[..]).collect();
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            ChaChaRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            _,
+        >(
+            self.general_params
+                .as_ref()
+                .ok_or_else(|| not_set_up("general params"))?,
+            self.circuit_proving_key
+                .as_ref()
+                .ok_or_else(|| not_set_up("proving key"))?,
+            &[circuit],
+            &[&instance_refs],
+            self.rng.to_owned().ok_or_else(|| not_set_up("rng"))?,
+            &mut transcript,
+        )?;
+        Ok(transcript.finalize())
+    }
+
+    /// Loads the verifier params and verifying key written by a previous
+    /// setup, which is all `verify` needs
+    pub fn load_verifier(&mut self) -> Result<(), Error> {
+        let path = self.verifier_params_path();
+        self.verifier_params = Some(
+            self.read_file(&path, |mut reader| {
+                ParamsKZG::<Bn256>::read_custom(&mut reader, self.serde_format)
+            })?
+            .ok_or_else(|| missing_file(&path))?,
+        );
+        let path = self.verifying_key_path();
+        self.circuit_verifying_key = Some(
+            self.read_file(&path, |mut reader| {
+                VerifyingKey::<G1Affine>::read::<_, CallCircuit>(&mut reader, self.serde_format)
+            })?
+            .ok_or_else(|| missing_file(&path))?,
+        );
+        Ok(())
+    }
+
+    /// Verifies a proof against an instance, which the verifier should build
+    /// itself, see `verifier::expected_instance`
+    #[instrument(name = "verification", skip_all, fields(k = self.degree))]
+    pub fn verify(&self, proof: &[u8], instance: Vec<Vec<Fr>>) -> Result<(), Error> {
+        let verifier_params = self
+            .verifier_params
+            .as_ref()
+            .ok_or_else(|| not_set_up("verifier params"))?;
+        let verifying_key = self
+            .verifying_key()
+            .ok_or_else(|| not_set_up("verifying key"))?;
+        let instance_refs: Vec<&[Fr]> = instance.iter().map(|v| &v[..]).collect();
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+            SingleStrategy<'_, Bn256>,
+        >(
+            verifier_params,
+            verifying_key,
+            SingleStrategy::new(verifier_params),
+            &[&instance_refs],
+            &mut transcript,
+        )
+        .map_err(|err| Error::proof(format!("invalid proof: {err}")))
+    }
+
+    /// The verifying key, part of the proving key once that is set up
+    pub fn verifying_key(&self) -> Option<&VerifyingKey<G1Affine>> {
+        match &self.circuit_proving_key {
+            Some(pk) => Some(pk.get_vk()),
+            None => self.circuit_verifying_key.as_ref(),
+        }
+    }
+
+    pub fn general_params_path(&self) -> PathBuf {
+        self.dir_path
+            .join(format!("kzg_general_params_{}", self.degree))
+    }
+
+    pub fn verifier_params_path(&self) -> PathBuf {
+        self.dir_path
+            .join(format!("kzg_verifier_params_{}", self.degree))
+    }
+
+    pub fn verifying_key_path(&self) -> PathBuf {
+        self.dir_path
+            .join(format!("circuit_verifying_key_{}", self.degree))
+    }
+
+    pub fn proving_key_path(&self) -> PathBuf {
+        self.dir_path
+            .join(format!("circuit_proving_key_{}", self.degree))
+    }
+
+    /// Reads a params or key file through a buffer, `None` if the file does
+    /// not exist yet. The whole key still ends up in memory, only the
+    /// `SerdeFormat` changes how fast it is read.
+    fn read_file<T>(
+        &self,
+        path: &Path,
+        read: impl FnOnce(&mut dyn Read) -> io::Result<T>,
+    ) -> Result<Option<T>, Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        debug!(path = %path.display(), "reading");
+        read(&mut BufReader::new(file))
+            .map(Some)
+            .map_err(|err| Error::proof(format!("cannot read {}: {err}", path.display())))
+    }
+
+    /// Writes a params or key file through a temporary file, so that an
+    /// interrupted write never leaves a truncated file behind
+    fn write_file(
+        path: &Path,
+        write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), Error> {
+        debug!(path = %path.display(), "writing");
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        write(&mut writer)?;
+        writer.flush()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn setup_general_params(&mut self) -> Result<(), Error> {
+        self.rng = Some(ChaChaRng::seed_from_u64(2));
+        let path = self.general_params_path();
+        let general_params = match self.read_file(&path, |mut reader| {
+            ParamsKZG::<Bn256>::read_custom(&mut reader, self.serde_format)
+        })? {
+            Some(general_params) => general_params,
+            None => {
+                info!("setting up general params");
+                let general_params = ParamsKZG::<Bn256>::setup(
+                    self.degree,
+                    self.rng.as_mut().ok_or_else(|| not_set_up("rng"))?,
+                );
+                Self::write_file(&path, |mut writer| {
+                    general_params.write_custom(&mut writer, self.serde_format)
+                })?;
+                general_params
+            }
+        };
+        self.general_params = Some(general_params);
+        Ok(())
+    }
+
+    fn setup_verifier_params(&mut self) -> Result<(), Error> {
+        let path = self.verifier_params_path();
+        let verifier_params = match self.read_file(&path, |mut reader| {
+            ParamsKZG::<Bn256>::read_custom(&mut reader, self.serde_format)
+        })? {
+            Some(verifier_params) => verifier_params,
+            None => {
+                info!("setting up verifier params");
+                let verifier_params = self
+                    .general_params
+                    .as_ref()
+                    .ok_or_else(|| not_set_up("general params"))?
+                    .verifier_params()
+                    .to_owned();
+                Self::write_file(&path, |mut writer| {
+                    verifier_params.write_custom(&mut writer, self.serde_format)
+                })?;
+                verifier_params
+            }
+        };
+        self.verifier_params = Some(verifier_params);
+        Ok(())
+    }
+
+    /// Reads the proving key written by a previous setup, which needs no
+    /// circuit. Returns false if there is none yet.
+    pub fn load_circuit(&mut self) -> Result<bool, Error> {
+        let proving_key_path = self.proving_key_path();
+        match self.read_file(&proving_key_path, |mut reader| {
+            ProvingKey::<G1Affine>::read::<_, CallCircuit>(&mut reader, self.serde_format)
+        })? {
+            Some(pk) => {
+                self.circuit_proving_key = Some(pk);
+                self.circuit_verifying_key = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Reads the proving key, or generates and writes it along with the
+    /// verifying key. The verifying key is only kept separately when there
+    /// is no proving key yet, see `verifying_key`.
+    #[instrument(name = "keygen", skip_all, fields(k = self.degree))]
+    pub fn setup_circuit(&mut self, circuit: CallCircuit) -> Result<(), Error> {
+        if self.load_circuit()? {
+            return Ok(());
+        }
+
+        let proving_key_path = self.proving_key_path();
+        let general_params = self
+            .general_params
+            .as_ref()
+            .ok_or_else(|| not_set_up("general params"))?;
+        let verifying_key_path = self.verifying_key_path();
+        let vk = match self.read_file(&verifying_key_path, |mut reader| {
+            VerifyingKey::<G1Affine>::read::<_, CallCircuit>(&mut reader, self.serde_format)
+        })? {
+            Some(vk) => vk,
+            None => {
+                info!("setting up verifying key");
+                let vk = keygen_vk(general_params, &circuit)?;
+                Self::write_file(&verifying_key_path, |mut writer| {
+                    vk.write(&mut writer, self.serde_format)
+                })?;
+                vk
+            }
+        };
+
+        info!("setting up proving key");
+        let pk = keygen_pk(general_params, vk, &circuit)?;
+        Self::write_file(&proving_key_path, |mut writer| {
+            pk.write(&mut writer, self.serde_format)
+        })?;
+        self.circuit_proving_key = Some(pk);
+        self.circuit_verifying_key = None;
+        Ok(())
+    }
+
+    /// Rewrites the cached params and keys of this prover's degree from its
+    /// format into `to`, e.g. into `RawBytesUnchecked` which loads fastest.
+    /// Files that do not exist are skipped, returns the converted paths.
+    pub fn convert_files(&self, to: SerdeFormat) -> Result<Vec<PathBuf>, Error> {
+        let mut converted = Vec::new();
+        for path in [self.general_params_path(), self.verifier_params_path()] {
+            if let Some(params) = self.read_file(&path, |mut reader| {
+                ParamsKZG::<Bn256>::read_custom(&mut reader, self.serde_format)
+            })? {
+                Self::write_file(&path, |mut writer| params.write_custom(&mut writer, to))?;
+                converted.push(path);
+            }
+        }
+        let path = self.verifying_key_path();
+        if let Some(vk) = self.read_file(&path, |mut reader| {
+            VerifyingKey::<G1Affine>::read::<_, CallCircuit>(&mut reader, self.serde_format)
+        })? {
+            Self::write_file(&path, |mut writer| vk.write(&mut writer, to))?;
+            converted.push(path);
+        }
+        let path = self.proving_key_path();
+        if let Some(pk) = self.read_file(&path, |mut reader| {
+            ProvingKey::<G1Affine>::read::<_, CallCircuit>(&mut reader, self.serde_format)
+        })? {
+            Self::write_file(&path, |mut writer| pk.write(&mut writer, to))?;
+            converted.push(path);
+        }
+        Ok(converted)
+    }
+}
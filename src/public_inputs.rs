@@ -0,0 +1,205 @@
+use eth_types::Fr;
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+
+use crate::{
+    error::Error,
+    prover::{fr_from_hex, RANDOMNESS},
+    types::zkevm_types::H256,
+};
+
+/// Field elements in the instance of a `CallCircuit` proof
+pub const INSTANCE_LEN: usize = 5;
+
+/// Instance of the `PiCircuit`, which is the only sub-circuit of the
+/// `SuperCircuit` with public inputs, so this is the whole instance of a
+/// `CallCircuit` proof.
+///
+/// | index | value                                                   |
+/// |-------|---------------------------------------------------------|
+/// | 0     | `rand_rpi`, randomness of `rpi_rlc`, `RANDOMNESS + 1`   |
+/// | 1     | `rpi_rlc`, rlc of the raw public inputs                 |
+/// | 2     | chain id                                                |
+/// | 3     | rlc of the state root bytes, see `root_rlc`             |
+/// | 4     | rlc of the previous state root bytes, see `root_rlc`    |
+///
+/// The raw public inputs are the block constants, the history hashes, the
+/// state roots and every field of the transactions along with their
+/// calldata, padded to `MAX_TXS` and `MAX_CALLDATA`, plus the return data
+/// added by the zkevm-circuits fork. The block number and hash, the caller,
+/// `to`, the calldata and the return data only enter the instance through
+/// `rpi_rlc`. Mapping them needs the raw public inputs rebuilt exactly as the
+/// fork's `PiCircuit` lays them out, which is not done, so none of them can
+/// be read back or checked from the instance.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicInputs {
+    pub rand_rpi: Fr,
+    pub rpi_rlc: Fr,
+    pub chain_id: u64,
+    pub state_root_rlc: Fr,
+    pub prev_state_root_rlc: Fr,
+}
+
+fn malformed(message: impl std::fmt::Display) -> Error {
+    Error::proof(format!("malformed public inputs: {message}"))
+}
+
+/// rlc of the bytes of a state root as the `PiCircuit` commits to it, the
+/// first byte gets the lowest power of `RANDOMNESS`
+pub fn root_rlc(root: &H256) -> Fr {
+    let randomness = Fr::from(RANDOMNESS);
+    root.as_bytes().iter().rev().fold(Fr::zero(), |acc, byte| {
+        acc * randomness + Fr::from(*byte as u64)
+    })
+}
+
+fn read_u64(value: &Fr, name: &str) -> Result<u64, Error> {
+    let repr = value.to_repr();
+    if repr[8..].iter().any(|byte| *byte != 0) {
+        return Err(malformed(format!("{name} does not fit in 8 bytes")));
+    }
+    Ok(u64::from_le_bytes(repr[..8].try_into().unwrap()))
+}
+
+impl PublicInputs {
+    pub fn from_instance(instance: &[Fr]) -> Result<Self, Error> {
+        if instance.len() != INSTANCE_LEN {
+            return Err(malformed(format!(
+                "expected {INSTANCE_LEN} elements, got {}",
+                instance.len()
+            )));
+        }
+        if instance[0] != Fr::from(RANDOMNESS + 1) {
+            return Err(malformed(format!(
+                "rand_rpi is not {}, the proof is for another circuit",
+                RANDOMNESS + 1
+            )));
+        }
+        Ok(Self {
+            rand_rpi: instance[0],
+            rpi_rlc: instance[1],
+            chain_id: read_u64(&instance[2], "chain id")?,
+            state_root_rlc: instance[3],
+            prev_state_root_rlc: instance[4],
+        })
+    }
+
+    /// Decodes the hex encoded instance of a `ProofBundle`
+    pub fn from_hex_instance(instance: &[String]) -> Result<Self, Error> {
+        let instance = instance
+            .iter()
+            .map(|value| fr_from_hex(value))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_instance(&instance)
+    }
+
+    pub fn to_instance(&self) -> Vec<Fr> {
+        vec![
+            self.rand_rpi,
+            self.rpi_rlc,
+            Fr::from(self.chain_id),
+            self.state_root_rlc,
+            self.prev_state_root_rlc,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{root_rlc, PublicInputs, INSTANCE_LEN};
+    #[cfg(feature = "anvil")]
+    use crate::{
+        call::tests::{local_session, STORAGE_READER},
+        call::CallRequest,
+        prover::build_circuit,
+        types::zkevm_types::Bytes,
+    };
+    use crate::{
+        prover::{fr_to_hex, RANDOMNESS},
+        types::zkevm_types::H256,
+    };
+    use eth_types::Fr;
+
+    fn public_inputs() -> PublicInputs {
+        PublicInputs {
+            rand_rpi: Fr::from(RANDOMNESS + 1),
+            rpi_rlc: Fr::from(0x1234),
+            chain_id: 11155111,
+            state_root_rlc: root_rlc(&H256::repeat_byte(0xcd)),
+            prev_state_root_rlc: root_rlc(&H256::repeat_byte(0xef)),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let public_inputs = public_inputs();
+        let instance = public_inputs.to_instance();
+        assert_eq!(instance.len(), INSTANCE_LEN);
+        assert_eq!(
+            PublicInputs::from_instance(&instance).unwrap(),
+            public_inputs
+        );
+
+        let hex_instance: Vec<String> = instance.iter().map(fr_to_hex).collect();
+        assert_eq!(
+            PublicInputs::from_hex_instance(&hex_instance).unwrap(),
+            public_inputs
+        );
+    }
+
+    #[test]
+    fn test_root_rlc() {
+        assert_eq!(root_rlc(&H256::zero()), Fr::zero());
+        let mut root = H256::zero();
+        root.0[0] = 1;
+        assert_eq!(root_rlc(&root), Fr::one());
+        root.0[1] = 1;
+        assert_eq!(root_rlc(&root), Fr::from(RANDOMNESS + 1));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let instance = public_inputs().to_instance();
+        assert!(PublicInputs::from_instance(&instance[..INSTANCE_LEN - 1]).is_err());
+
+        // chain id wider than 8 bytes
+        let mut tampered = instance.clone();
+        tampered[2] = -Fr::from(1);
+        assert!(PublicInputs::from_instance(&tampered).is_err());
+
+        // randomness of another circuit
+        let mut tampered = instance;
+        tampered[0] = Fr::from(RANDOMNESS);
+        assert!(PublicInputs::from_instance(&tampered).is_err());
+    }
+
+    /// The instance the circuit is actually built with decodes, and commits to
+    /// the state root of the block the call runs on top of
+    #[cfg(feature = "anvil")]
+    #[tokio::test]
+    async fn test_circuit_instance() {
+        let mut session = local_session().await;
+        let request = CallRequest::new(STORAGE_READER, Bytes::default());
+        let mut call_witness = session.witness(&request).await.unwrap();
+        let parent_number = call_witness.tx.block_number.unwrap().as_usize() - 1;
+        let parent = session
+            .builder
+            .backend
+            .block_by_number(parent_number)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (_, instance) = build_circuit(&mut call_witness);
+        let public_inputs = PublicInputs::from_instance(&instance[0]).unwrap();
+        assert_eq!(public_inputs.chain_id, 31337);
+        assert_eq!(
+            public_inputs.prev_state_root_rlc,
+            root_rlc(&parent.state_root)
+        );
+        assert_eq!(
+            public_inputs.state_root_rlc,
+            root_rlc(&call_witness.inputs.block.state_root)
+        );
+        assert_eq!(public_inputs.to_instance(), instance[0]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+use ethers::abi::{decode, ParamType, Token};
+use ethers_core::utils::hex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    types::zkevm_types::{Bytes, Word},
+};
+
+/// Selector of `Error(string)`, used by `require` and `revert("...")`
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`, used by failed asserts and checked arithmetic
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Decoded revert data of a failed call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RevertReason {
+    /// `revert()` or a failure without any data
+    Empty,
+    /// `Error(string)`
+    Error { message: String },
+    /// `Panic(uint256)`, see `panic_description`
+    Panic { code: Word },
+    /// custom error or any other data, `selector` is empty if there are
+    /// fewer than 4 bytes
+    Custom { selector: Bytes, data: Bytes },
+}
+
+impl RevertReason {
+    pub fn decode(data: &[u8]) -> Self {
+        if data.is_empty() {
+            return RevertReason::Empty;
+        }
+        if data.len() < 4 {
+            return RevertReason::Custom {
+                selector: Bytes::default(),
+                data: data.to_vec().into(),
+            };
+        }
+        let (selector, args) = data.split_at(4);
+        match selector {
+            s if s == ERROR_SELECTOR => {
+                if let Ok(mut tokens) = decode(&[ParamType::String], args) {
+                    if let Some(Token::String(message)) = tokens.pop() {
+                        return RevertReason::Error { message };
+                    }
+                }
+            }
+            s if s == PANIC_SELECTOR => {
+                if let Ok(mut tokens) = decode(&[ParamType::Uint(256)], args) {
+                    if let Some(Token::Uint(code)) = tokens.pop() {
+                        let mut bytes = [0u8; 32];
+                        code.to_big_endian(&mut bytes);
+                        return RevertReason::Panic {
+                            code: Word::from_big_endian(&bytes),
+                        };
+                    }
+                }
+            }
+            _ => {}
+        }
+        // malformed `Error` or `Panic` data is kept as is
+        RevertReason::Custom {
+            selector: selector.to_vec().into(),
+            data: args.to_vec().into(),
+        }
+    }
+}
+
+/// What the solidity compiler means by a panic code
+pub fn panic_description(code: Word) -> &'static str {
+    if code > Word::from(u8::MAX) {
+        return "unknown panic";
+    }
+    match code.as_u32() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to an uninitialized function",
+        _ => "unknown panic",
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Empty => write!(f, "no revert data"),
+            RevertReason::Error { message } => write!(f, "Error({message:?})"),
+            RevertReason::Panic { code } => {
+                write!(f, "Panic({code:#x}): {}", panic_description(*code))
+            }
+            RevertReason::Custom { selector, data } => write!(
+                f,
+                "custom error 0x{} with data 0x{}",
+                hex::encode(selector),
+                hex::encode(data)
+            ),
+        }
+    }
+}
+
+/// What to do with a call that reverts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnRevert {
+    /// fail instead of proving, a revert is usually not what was meant
+    #[default]
+    Refuse,
+    /// prove the reverting execution like any other call. The revert is
+    /// read from the trace, the public inputs have no success flag
+    Allow,
+}
+
+impl std::str::FromStr for OnRevert {
+    type Err = Error;
+
+    fn from_str(on_revert: &str) -> Result<Self, Error> {
+        match on_revert {
+            "refuse" => Ok(OnRevert::Refuse),
+            "allow" => Ok(OnRevert::Allow),
+            _ => Err(Error::config(format!(
+                "invalid revert handling {on_revert}, expected refuse or allow"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RevertReason, ERROR_SELECTOR, PANIC_SELECTOR};
+    use crate::types::zkevm_types::Word;
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(RevertReason::decode(&[]), RevertReason::Empty);
+
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(encode(&[Token::String("insufficient balance".to_string())]));
+        let reason = RevertReason::decode(&data);
+        assert_eq!(
+            reason,
+            RevertReason::Error {
+                message: "insufficient balance".to_string()
+            }
+        );
+        assert_eq!(reason.to_string(), "Error(\"insufficient balance\")");
+
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend(encode(&[Token::Uint(0x11.into())]));
+        let reason = RevertReason::decode(&data);
+        assert_eq!(
+            reason,
+            RevertReason::Panic {
+                code: Word::from(0x11)
+            }
+        );
+        assert_eq!(
+            reason.to_string(),
+            "Panic(0x11): arithmetic overflow or underflow"
+        );
+
+        // InsufficientBalance(uint256,uint256)
+        let mut data = vec![0xcf, 0x47, 0x91, 0x81];
+        data.extend(encode(&[Token::Uint(1.into()), Token::Uint(2.into())]));
+        assert!(matches!(
+            RevertReason::decode(&data),
+            RevertReason::Custom { selector, data } if selector.len() == 4 && data.len() == 64
+        ));
+        // truncated Error(string) data
+        assert!(matches!(
+            RevertReason::decode(&ERROR_SELECTOR),
+            RevertReason::Custom { .. }
+        ));
+    }
+}
@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::{
+    call::{blocking, CallRequest, ProvingSession},
+    config::BlockTag,
+    error::Error,
+    jobs::{JobStatus, JobStore},
+    prover::ProofBundle,
+    revert::OnRevert,
+    types::zkevm_types::{Address, BlockNumber, Bytes, Word, U64},
+};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const SERVER_ERROR: i64 = -32000;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        RpcError::new(SERVER_ERROR, err.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Call object of `zk_call`, same shape as the one of `eth_call`
+#[derive(Clone, Debug, Deserialize)]
+pub struct CallObject {
+    /// has to be the address of the key calls are signed with, see
+    /// `ProverOptions::signer`
+    #[serde(default)]
+    pub from: Option<Address>,
+    /// missing for a contract creation
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default, alias = "input")]
+    pub data: Option<Bytes>,
+    #[serde(default)]
+    pub value: Option<Word>,
+    #[serde(default)]
+    pub gas: Option<U64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ZkCallOptions {
+    /// return a job id right away instead of waiting for the proof
+    #[serde(default, rename = "async")]
+    pub is_async: bool,
+    /// allow a reverting call instead of failing, see `OnRevert`
+    #[serde(default, rename = "onRevert")]
+    pub on_revert: OnRevert,
+}
+
+/// Serves `zk_call`, which mirrors `eth_call` but also proves the call, and
+/// `zk_getJob` to poll calls submitted with `{"async": true}`.
+pub struct ZkCallService {
+    session: Mutex<ProvingSession>,
+    jobs: JobStore,
+}
+
+fn parse_param<T: for<'de> Deserialize<'de>>(
+    params: &[Value],
+    index: usize,
+) -> Result<Option<T>, RpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|err| RpcError::new(INVALID_PARAMS, format!("param {index}: {err}"))),
+    }
+}
+
+/// The session forks at a number or at the latest block, other tags are
+/// refused rather than proven at the latest block
+fn block_tag(block: BlockNumber) -> Result<Option<BlockTag>, RpcError> {
+    match block {
+        BlockNumber::Latest => Ok(None),
+        BlockNumber::Number(block_number) => Ok(Some(BlockTag::Number(block_number.as_usize()))),
+        tag => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("block {tag} is not supported, pass latest or a block number"),
+        )),
+    }
+}
+
+fn params_array(params: &Value) -> Result<&[Value], RpcError> {
+    match params {
+        Value::Array(params) => Ok(params),
+        _ => Err(RpcError::new(INVALID_PARAMS, "params must be an array")),
+    }
+}
+
+impl ZkCallService {
+    pub fn new(session: ProvingSession) -> Self {
+        Self {
+            session: Mutex::new(session),
+            jobs: JobStore::default(),
+        }
+    }
+
+    pub async fn handle_raw(self: &Arc<Self>, body: &str) -> RpcResponse {
+        match serde_json::from_str::<Value>(body) {
+            Ok(value) => match serde_json::from_value::<RpcRequest>(value) {
+                Ok(request) => self.handle(request).await,
+                Err(err) => RpcResponse::new(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, err.to_string())),
+                ),
+            },
+            Err(err) => RpcResponse::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            ),
+        }
+    }
+
+    pub async fn handle(self: &Arc<Self>, request: RpcRequest) -> RpcResponse {
+        let result = match request.method.as_str() {
+            "zk_call" => self.zk_call(&request.params).await,
+            "zk_getJob" => self.zk_get_job(&request.params),
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method {method} not found"),
+            )),
+        };
+        RpcResponse::new(request.id, result)
+    }
+
+    async fn zk_call(self: &Arc<Self>, params: &Value) -> Result<Value, RpcError> {
+        let params = params_array(params)?;
+        let call: CallObject = parse_param(params, 0)?
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing call object"))?;
+        let block = block_tag(parse_param(params, 1)?.unwrap_or_default())?;
+        let options: ZkCallOptions = parse_param(params, 2)?.unwrap_or_default();
+
+        if options.is_async {
+            let id = self.jobs.create();
+            let service = self.clone();
+            tokio::spawn(async move {
+                service.jobs.update(id, JobStatus::Running);
+                let status = match service.prove(call, block, options.on_revert).await {
+                    Ok(bundle) => JobStatus::Done {
+                        result: bundle.return_data.clone(),
+                        proof: bundle,
+                    },
+                    Err(err) => JobStatus::Failed {
+                        error: err.to_string(),
+                    },
+                };
+                service.jobs.update(id, status);
+            });
+            Ok(json!({ "jobId": U64::from(id) }))
+        } else {
+            let bundle = self.prove(call, block, options.on_revert).await?;
+            Ok(json!({ "result": bundle.return_data, "proof": bundle }))
+        }
+    }
+
+    fn zk_get_job(&self, params: &Value) -> Result<Value, RpcError> {
+        let params = params_array(params)?;
+        let id: U64 = parse_param(params, 0)?
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing job id"))?;
+        let status = self
+            .jobs
+            .get(id.as_u64())
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "unknown job id"))?;
+        Ok(serde_json::to_value(status).map_err(Error::from)?)
+    }
+
+    async fn prove(
+        &self,
+        call: CallObject,
+        block: Option<BlockTag>,
+        on_revert: OnRevert,
+    ) -> Result<ProofBundle, Error> {
+        let request = CallRequest {
+            from: call.from,
+            to: call.to,
+            data: call.data.unwrap_or_default(),
+            value: call.value,
+            gas: call.gas.map(|gas| Word::from(gas.as_u64())),
+            block,
+            on_revert,
+            ..Default::default()
+        };
+        let (prepared, prover) = {
+            let mut session = self.session.lock().await;
+            let call_witness = session.witness(&request).await?;
+            (session.prepare(call_witness, None)?, session.prover())
+        };
+        blocking(move || {
+            let prover = prover.get(&prepared.circuit)?;
+            prepared.prove(prover.as_deref())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::{ZkCallService, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR};
+    use crate::{
+        call::tests::{local_session, STORAGE_READER},
+        types::zkevm_types::{Bytes, H256},
+    };
+
+    #[tokio::test]
+    async fn test_errors() {
+        let service = Arc::new(ZkCallService::new(local_session().await));
+
+        let code = |body: serde_json::Value| {
+            let service = service.clone();
+            async move {
+                let response = service.handle_raw(&body.to_string()).await;
+                response.error.map(|error| error.code)
+            }
+        };
+
+        let response = service.handle_raw("{").await;
+        assert_eq!(response.error.unwrap().code, PARSE_ERROR);
+        assert_eq!(code(json!({ "id": 1 })).await, Some(INVALID_REQUEST));
+        assert_eq!(
+            code(json!({ "id": 1, "method": "eth_call", "params": [] })).await,
+            Some(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            code(json!({ "id": 1, "method": "zk_call", "params": [] })).await,
+            Some(INVALID_PARAMS)
+        );
+        assert_eq!(
+            code(json!({ "id": 1, "method": "zk_call", "params": [{ "to": "0x12" }] })).await,
+            Some(INVALID_PARAMS)
+        );
+        for tag in ["earliest", "safe", "finalized", "pending"] {
+            assert_eq!(
+                code(json!({ "id": 1, "method": "zk_call", "params": [{ "to": STORAGE_READER }, tag] }))
+                    .await,
+                Some(INVALID_PARAMS)
+            );
+        }
+        assert_eq!(
+            code(json!({ "id": 1, "method": "zk_getJob", "params": ["0x1"] })).await,
+            Some(INVALID_PARAMS)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zk_call() {
+        let service = Arc::new(ZkCallService::new(local_session().await));
+        let body = json!({
+            "id": 1,
+            "method": "zk_call",
+            "params": [{ "to": STORAGE_READER }, "latest"]
+        });
+        let response = service.handle_raw(&body.to_string()).await;
+        assert_eq!(response.error, None);
+        let result = response.result.unwrap();
+        let return_data: Bytes = serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(
+            return_data,
+            Bytes::from(H256::from_low_u64_be(42).as_bytes().to_vec())
+        );
+        assert_eq!(result["proof"]["success"], json!(true));
+    }
+}
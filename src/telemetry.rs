@@ -0,0 +1,171 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::error::Error;
+
+/// Timing of a closed span, e.g. one of the proving phases
+#[derive(Clone, Debug, Serialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub duration_ms: f64,
+    pub fields: BTreeMap<String, Value>,
+    /// peak resident memory of the process when the span closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_memory_kb: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunSummary {
+    pub total_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_memory_kb: Option<u64>,
+    pub phases: Vec<PhaseTiming>,
+}
+
+#[derive(Default)]
+struct FieldVisitor(BTreeMap<String, Value>);
+
+impl Visit for FieldVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+struct SpanTiming {
+    start: Instant,
+    fields: FieldVisitor,
+}
+
+/// Records the duration and fields of every span as it closes
+struct TimingLayer {
+    phases: Arc<Mutex<Vec<PhaseTiming>>>,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimingLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = FieldVisitor::default();
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(SpanTiming {
+                start: Instant::now(),
+                fields,
+            });
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                values.record(&mut timing.fields);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id) {
+            if let Some(timing) = span.extensions().get::<SpanTiming>() {
+                if let Ok(mut phases) = self.phases.lock() {
+                    phases.push(PhaseTiming {
+                        name: span.name().to_string(),
+                        duration_ms: timing.start.elapsed().as_secs_f64() * 1000.0,
+                        fields: timing.fields.0.clone(),
+                        peak_memory_kb: peak_memory_kb(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Peak resident set size, only available on linux
+pub fn peak_memory_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+/// Logging and timing of a run. Logs go to stdout, filtered with `RUST_LOG`
+/// (default `info`), phase timings are kept for `write_summary`.
+pub struct Telemetry {
+    start: Instant,
+    phases: Arc<Mutex<Vec<PhaseTiming>>>,
+}
+
+impl Telemetry {
+    pub fn init(json_logs: bool) -> Self {
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+        let timing = TimingLayer {
+            phases: phases.clone(),
+        };
+        let registry = tracing_subscriber::registry().with(filter).with(timing);
+        // ignore the error of a subscriber already being set, e.g. in tests
+        let _ = if json_logs {
+            registry
+                .with(tracing_subscriber::fmt::layer().json())
+                .try_init()
+        } else {
+            registry.with(tracing_subscriber::fmt::layer()).try_init()
+        };
+        Self {
+            start: Instant::now(),
+            phases,
+        }
+    }
+
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            total_ms: self.start.elapsed().as_secs_f64() * 1000.0,
+            peak_memory_kb: peak_memory_kb(),
+            phases: self
+                .phases
+                .lock()
+                .map(|phases| phases.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn write_summary(&self, path: &Path) -> Result<(), Error> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &self.summary())?;
+        Ok(())
+    }
+}
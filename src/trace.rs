@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use crate::types::zkevm_types::{Address, Bytes, GethExecTrace, H256, U256};
+
+/// Output of `debug_traceTransaction` for the tracers this crate understands
+#[derive(Clone, Debug)]
+pub enum TraceFrame {
+    /// default struct log tracer, consumed by the circuit input builder
+    StructLogs(GethExecTrace),
+    /// built-in `callTracer`
+    Call(CallTrace),
+    /// built-in `prestateTracer`
+    Prestate(PrestateTrace),
+    /// any other tracer output, kept as raw json
+    Other(serde_json::Value),
+}
+
+/// Call tree produced by the built-in `callTracer`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallTrace {
+    pub typ: String,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: Option<U256>,
+    pub gas: U256,
+    pub gas_used: U256,
+    pub input: Bytes,
+    pub output: Option<Bytes>,
+    pub error: Option<String>,
+    pub calls: Vec<CallTrace>,
+}
+
+impl CallTrace {
+    /// Depth-first iterator over this frame and all of its sub-calls
+    pub fn iter(&self) -> impl Iterator<Item = &CallTrace> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let frame = stack.pop()?;
+            stack.extend(frame.calls.iter().rev());
+            Some(frame)
+        })
+    }
+
+    /// Follows the chain of sub-calls that returned exactly the same bytes as
+    /// this frame (e.g. proxy -> implementation) and returns the deepest one,
+    /// i.e. the call which actually produced the return data. A frame without
+    /// output has no return data to follow.
+    pub fn return_data_origin(&self) -> &CallTrace {
+        let mut frame = self;
+        while let Some(child) = frame.output.as_ref().and_then(|output| {
+            frame
+                .calls
+                .iter()
+                .rev()
+                .find(|child| child.error.is_none() && child.output.as_ref() == Some(output))
+        }) {
+            frame = child;
+        }
+        frame
+    }
+}
+
+/// Account state before execution, as reported by the built-in `prestateTracer`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrestateAccount {
+    pub balance: Option<U256>,
+    pub nonce: Option<U256>,
+    pub code: Option<Bytes>,
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// Accounts and storage touched by a transaction, with their values before execution
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrestateTrace(pub BTreeMap<Address, PrestateAccount>);
+
+#[cfg(test)]
+mod tests {
+    use super::CallTrace;
+    use crate::types::zkevm_types::Address;
+
+    fn frame(to: u8, output: Option<Vec<u8>>, calls: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            typ: "CALL".to_string(),
+            to: Some(Address::repeat_byte(to)),
+            output: output.map(Into::into),
+            calls,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_return_data_origin() {
+        let implementation = frame(2, Some(vec![42]), vec![frame(3, Some(vec![1]), vec![])]);
+        let proxy = frame(1, Some(vec![42]), vec![implementation.clone()]);
+        assert_eq!(proxy.return_data_origin(), &implementation);
+
+        // a proxy without output does not walk into sub-calls without output
+        let proxy = frame(1, None, vec![frame(2, None, vec![])]);
+        assert_eq!(proxy.return_data_origin(), &proxy);
+    }
+}
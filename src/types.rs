@@ -23,12 +23,127 @@ pub mod zkevm_types {
 }
 
 pub mod anvil_types {
-    pub use anvil_core::eth::transaction::EthTransactionRequest;
+    #[cfg(feature = "anvil")]
+    pub use anvil_core::{eth::transaction::EthTransactionRequest, types::Forking};
     pub use ethers::types::{
         transaction::eip2930::AccessList, Address, Block, BlockId, BlockNumber, Bloom, Bytes,
-        EIP1186ProofResponse, GethDebugTracingOptions, GethTrace, Log, OtherFields, StorageProof,
-        Transaction, TransactionReceipt, TxHash, H160, H256, H64, U256, U64,
+        EIP1186ProofResponse, GethDebugBuiltInTracerType, GethDebugTracerType,
+        GethDebugTracingOptions, GethTrace, Log, OtherFields, StorageProof, Transaction,
+        TransactionReceipt, TxHash, H160, H256, H64, U256, U64,
     };
     pub type EthBlockFull = Block<Transaction>;
     pub type EthBlockHeader = Block<TxHash>;
 }
+
+pub mod op_stack_types {
+    use super::zkevm_types::{OtherFields, Transaction, TransactionReceipt, H256, U256, U64};
+    use serde::Deserialize;
+
+    /// EIP-2718 type of the OP-stack deposit transaction
+    pub const DEPOSIT_TX_TYPE: u64 = 0x7e;
+
+    /// Extra fields carried by an OP-stack deposit transaction
+    #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DepositFields {
+        pub source_hash: H256,
+        #[serde(default)]
+        pub mint: Option<U256>,
+        #[serde(default)]
+        pub is_system_tx: bool,
+    }
+
+    /// L1 data fee info attached to OP-stack receipts
+    #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct L1BlockInfo {
+        #[serde(default)]
+        pub l1_gas_price: Option<U256>,
+        #[serde(default)]
+        pub l1_gas_used: Option<U256>,
+        #[serde(default)]
+        pub l1_fee: Option<U256>,
+        // decimal string, e.g. "0.684"
+        #[serde(default)]
+        pub l1_fee_scalar: Option<String>,
+    }
+
+    fn deserialize_other<T: for<'de> Deserialize<'de>>(other: &OtherFields) -> Option<T> {
+        other.clone().deserialize_into().ok()
+    }
+
+    pub trait OpStackTransaction {
+        fn is_deposit(&self) -> bool;
+        fn deposit_fields(&self) -> Option<DepositFields>;
+    }
+
+    impl OpStackTransaction for Transaction {
+        fn is_deposit(&self) -> bool {
+            self.transaction_type == Some(U64::from(DEPOSIT_TX_TYPE))
+        }
+
+        fn deposit_fields(&self) -> Option<DepositFields> {
+            if !self.is_deposit() {
+                return None;
+            }
+            deserialize_other(&self.other)
+        }
+    }
+
+    pub trait OpStackReceipt {
+        fn l1_block_info(&self) -> Option<L1BlockInfo>;
+    }
+
+    impl OpStackReceipt for TransactionReceipt {
+        fn l1_block_info(&self) -> Option<L1BlockInfo> {
+            let info: L1BlockInfo = deserialize_other(&self.other)?;
+            if info == L1BlockInfo::default() {
+                None
+            } else {
+                Some(info)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_deposit_fields() {
+            let mut tx = Transaction {
+                transaction_type: Some(U64::from(DEPOSIT_TX_TYPE)),
+                ..Default::default()
+            };
+            tx.other = serde_json::from_value(serde_json::json!({
+                "sourceHash": "0xa8157ccf61bcdfbcb74a84ec1262e62644dd1e7e3614abcbd8db0c99a60049fc",
+                "mint": "0x0",
+                "isSystemTx": false,
+            }))
+            .unwrap();
+            let fields = tx.deposit_fields().unwrap();
+            assert_eq!(fields.mint, Some(U256::zero()));
+            assert!(!fields.is_system_tx);
+
+            tx.transaction_type = Some(U64::from(2));
+            assert!(tx.deposit_fields().is_none());
+        }
+
+        #[test]
+        fn test_l1_block_info() {
+            let mut rc = TransactionReceipt::default();
+            assert!(rc.l1_block_info().is_none());
+
+            rc.other = serde_json::from_value(serde_json::json!({
+                "l1GasPrice": "0x3b9aca00",
+                "l1GasUsed": "0x8d0",
+                "l1Fee": "0x1f4",
+                "l1FeeScalar": "0.684",
+            }))
+            .unwrap();
+            let info = rc.l1_block_info().unwrap();
+            assert_eq!(info.l1_gas_used, Some(U256::from(0x8d0)));
+            assert_eq!(info.l1_fee_scalar.as_deref(), Some("0.684"));
+        }
+    }
+}
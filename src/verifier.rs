@@ -0,0 +1,355 @@
+use eth_types::Fr;
+
+use crate::{
+    backend::ChainBackend,
+    error::Error,
+    overrides::StateOverride,
+    prover::{fr_from_hex, ProofBundle},
+    public_inputs::{root_rlc, PublicInputs},
+    types::zkevm_types::{Address, Bytes, EthBlockHeader, Word, H256},
+};
+
+/// What a caller wants a proof to attest: calling `to` with `calldata` and
+/// `value` from `caller` on top of the block `block_hash` returned
+/// `return_data`, or reverted with it as revert data if not `success`.
+/// If `create` the claim is a contract creation with `calldata` as init code
+/// and `to` has to be `None`.
+///
+/// The call is proven in a block built on top of `block_hash`, the fork
+/// block, so the state it runs on is the state after that block with
+/// `assumed_state` applied on top. The assumed state is metadata of the
+/// bundle, the proof does not commit to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallClaim {
+    pub chain_id: u64,
+    pub block_hash: H256,
+    pub caller: Address,
+    pub to: Option<Address>,
+    pub create: bool,
+    pub value: Word,
+    pub success: bool,
+    pub calldata: Bytes,
+    pub return_data: Bytes,
+    pub assumed_state: StateOverride,
+}
+
+fn mismatch(field: &str) -> Error {
+    Error::proof(format!("bundle does not match the claimed {field}"))
+}
+
+/// Checks a bundle against the claim and the trusted header of the claimed
+/// block, and returns the instance the bundle's proof has to verify against.
+///
+/// The instance is the bundle's, it commits to the chain id and to the state
+/// root of the claimed block as the previous state root, both checked here
+/// against the claim and the trusted header. The call itself only enters
+/// the instance through `rpi_rlc`, which is not rebuilt, see `PublicInputs`,
+/// so the call fields and the return data are only checked against the
+/// bundle. This is not a verification of the call: a valid proof of any
+/// call on top of the claimed block passes with a bundle claiming another.
+pub fn expected_instance(
+    claim: &CallClaim,
+    bundle: &ProofBundle,
+    header: &EthBlockHeader,
+) -> Result<Vec<Vec<Fr>>, Error> {
+    if header.hash != Some(claim.block_hash) {
+        return Err(Error::rpc(format!(
+            "header does not match the claimed block {:?}",
+            claim.block_hash
+        )));
+    }
+    let block_number = header
+        .number
+        .ok_or_else(|| Error::rpc("block without number"))?;
+    if bundle.block_number != block_number + 1 {
+        return Err(Error::proof(format!(
+            "bundle is proven in block {} and not on top of block {block_number}",
+            bundle.block_number
+        )));
+    }
+    if bundle.chain_id != Word::from(claim.chain_id) {
+        return Err(mismatch("chain id"));
+    }
+    if bundle.from != claim.caller {
+        return Err(mismatch("caller"));
+    }
+    if claim.create != claim.to.is_none() {
+        return Err(Error::config(
+            "a claimed creation has no destination and a claimed call has one",
+        ));
+    }
+    if bundle.create != bundle.to.is_none() {
+        return Err(Error::proof(
+            "bundle has a destination that does not match its creation flag",
+        ));
+    }
+    if bundle.create != claim.create {
+        return Err(mismatch("creation flag"));
+    }
+    if bundle.to != claim.to {
+        return Err(mismatch("destination"));
+    }
+    if bundle.value != claim.value {
+        return Err(mismatch("value"));
+    }
+    if bundle.calldata != claim.calldata {
+        return Err(mismatch("calldata"));
+    }
+    if bundle.success != claim.success {
+        return Err(mismatch("success"));
+    }
+    if bundle.return_data != claim.return_data {
+        return Err(mismatch("return data"));
+    }
+    if bundle.assumed_state != claim.assumed_state {
+        return Err(mismatch("assumed state"));
+    }
+
+    let instance = bundle
+        .instance
+        .iter()
+        .map(|value| fr_from_hex(value))
+        .collect::<Result<Vec<_>, _>>()?;
+    let public_inputs = PublicInputs::from_instance(&instance)?;
+    if public_inputs.chain_id != claim.chain_id {
+        return Err(Error::proof(format!(
+            "instance is for chain {} and not {}",
+            public_inputs.chain_id, claim.chain_id
+        )));
+    }
+    if public_inputs.prev_state_root_rlc != root_rlc(&header.state_root) {
+        return Err(Error::proof(format!(
+            "instance does not start from the state of block {:?}",
+            claim.block_hash
+        )));
+    }
+    Ok(vec![instance])
+}
+
+/// Same as `expected_instance`, looking up the header on a backend the
+/// verifier trusts
+pub async fn expected_instance_from_backend<B: ChainBackend>(
+    backend: &B,
+    claim: &CallClaim,
+    bundle: &ProofBundle,
+) -> Result<Vec<Vec<Fr>>, Error> {
+    let chain_id = backend.chain_id().await?;
+    if chain_id != Word::from(claim.chain_id) {
+        return Err(Error::config(format!(
+            "claim is for chain {} but the backend is on chain {chain_id}",
+            claim.chain_id
+        )));
+    }
+    let header = backend
+        .block_by_hash(claim.block_hash)
+        .await?
+        .ok_or_else(|| Error::rpc(format!("block {:?} not found", claim.block_hash)))?;
+    expected_instance(claim, bundle, &header)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "anvil")]
+    use super::expected_instance;
+    use super::{expected_instance_from_backend, CallClaim};
+    use crate::{
+        backend::MemoryBackend,
+        overrides::{AccountOverride, StateOverride},
+        prover::{fr_to_hex, ProofBundle, RANDOMNESS},
+        public_inputs::{root_rlc, PublicInputs},
+        types::zkevm_types::{Address, EthBlockFull, Word, H256, U256, U64},
+    };
+    #[cfg(feature = "anvil")]
+    use crate::{
+        call::{
+            tests::{local_session, STORAGE_READER},
+            CallRequest,
+        },
+        prover::mock_verify,
+        types::zkevm_types::Bytes,
+    };
+    use eth_types::Fr;
+
+    fn mock_bundle(claim: &CallClaim, prev_state_root: H256) -> ProofBundle {
+        let public_inputs = PublicInputs {
+            rand_rpi: Fr::from(RANDOMNESS + 1),
+            rpi_rlc: Fr::from(0x1234),
+            chain_id: claim.chain_id,
+            state_root_rlc: Fr::zero(),
+            prev_state_root_rlc: root_rlc(&prev_state_root),
+        };
+        ProofBundle {
+            chain_id: Word::from(claim.chain_id),
+            block_number: U64::from(11),
+            tx_hash: H256::repeat_byte(3),
+            from: claim.caller,
+            to: claim.to,
+            create: claim.create,
+            value: claim.value,
+            calldata: claim.calldata.clone(),
+            success: claim.success,
+            return_data: claim.return_data.clone(),
+            revert_reason: None,
+            signature: None,
+            decoded_return: None,
+            k: 19,
+            instance: public_inputs.to_instance().iter().map(fr_to_hex).collect(),
+            proof: None,
+            assumed_state: claim.assumed_state.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expected_instance() {
+        let mut backend = MemoryBackend::new(Word::from(1));
+        let block = EthBlockFull {
+            hash: Some(H256::repeat_byte(2)),
+            parent_hash: H256::repeat_byte(1),
+            number: Some(U64::from(10)),
+            state_root: H256::repeat_byte(0xbb),
+            ..Default::default()
+        };
+        backend.insert_block(block).unwrap();
+
+        let claim = CallClaim {
+            chain_id: 1,
+            block_hash: H256::repeat_byte(2),
+            caller: Address::zero(),
+            to: Some(Address::repeat_byte(0x35)),
+            create: false,
+            value: Word::from(7),
+            success: true,
+            calldata: vec![0x20, 0x96, 0x52, 0x55].into(),
+            return_data: vec![0u8; 32].into(),
+            assumed_state: StateOverride::default(),
+        };
+        let bundle = mock_bundle(&claim, H256::repeat_byte(0xbb));
+        let instance = expected_instance_from_backend(&backend, &claim, &bundle)
+            .await
+            .unwrap();
+        assert_eq!(
+            PublicInputs::from_hex_instance(&bundle.instance).unwrap(),
+            PublicInputs::from_instance(&instance[0]).unwrap()
+        );
+
+        // a proof starting from another state
+        let other_state = mock_bundle(&claim, H256::repeat_byte(0xcc));
+        assert!(
+            expected_instance_from_backend(&backend, &claim, &other_state)
+                .await
+                .is_err()
+        );
+        // a proof in a block not on top of the claimed one
+        let mut later = bundle.clone();
+        later.block_number = U64::from(12);
+        assert!(expected_instance_from_backend(&backend, &claim, &later)
+            .await
+            .is_err());
+        // a bundle of another call
+        let other_return = CallClaim {
+            return_data: vec![1u8; 32].into(),
+            ..claim.clone()
+        };
+        assert!(
+            expected_instance_from_backend(&backend, &other_return, &bundle)
+                .await
+                .is_err()
+        );
+
+        // a creation claimed for the bundle of a call
+        let creation = CallClaim {
+            to: None,
+            create: true,
+            ..claim.clone()
+        };
+        assert!(expected_instance_from_backend(&backend, &creation, &bundle)
+            .await
+            .is_err());
+
+        // a claim assuming other state than the proof
+        let mut assumed_state = StateOverride::default();
+        assumed_state.0.insert(
+            Address::repeat_byte(0x35),
+            AccountOverride {
+                balance: Some(U256::from(1)),
+                ..Default::default()
+            },
+        );
+        let other_assumed = CallClaim {
+            assumed_state,
+            ..claim.clone()
+        };
+        assert!(
+            expected_instance_from_backend(&backend, &other_assumed, &bundle)
+                .await
+                .is_err()
+        );
+
+        let wrong_chain = CallClaim {
+            chain_id: 5,
+            ..claim.clone()
+        };
+        assert!(
+            expected_instance_from_backend(&backend, &wrong_chain, &bundle)
+                .await
+                .is_err()
+        );
+        let unknown_block = CallClaim {
+            block_hash: H256::repeat_byte(3),
+            ..claim
+        };
+        assert!(
+            expected_instance_from_backend(&backend, &unknown_block, &bundle)
+                .await
+                .is_err()
+        );
+    }
+
+    /// The instance of an actual proving run is accepted for its fork block
+    #[cfg(feature = "anvil")]
+    #[tokio::test]
+    async fn test_expected_instance_of_proven_call() {
+        let mut session = local_session().await;
+        let request = CallRequest::new(STORAGE_READER, Bytes::default());
+        let call_witness = session.witness(&request).await.unwrap();
+        let prepared = session.prepare(call_witness, None).unwrap();
+        let fork_block_number = prepared.bundle.block_number.as_usize() - 1;
+        let header = session
+            .builder
+            .backend
+            .block_by_number(fork_block_number)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let claim = CallClaim {
+            chain_id: 31337,
+            block_hash: header.hash.unwrap(),
+            caller: prepared.bundle.from,
+            to: Some(STORAGE_READER),
+            create: false,
+            value: Word::zero(),
+            success: true,
+            calldata: Bytes::default(),
+            return_data: H256::from_low_u64_be(42).as_bytes().to_vec().into(),
+            assumed_state: prepared.bundle.assumed_state.clone(),
+        };
+        let instance = expected_instance(&claim, &prepared.bundle, &header).unwrap();
+        assert_eq!(instance, prepared.instance);
+        mock_verify(prepared.k, &prepared.circuit, instance).unwrap();
+
+        // nor for the state of an earlier block
+        let earlier = session
+            .builder
+            .backend
+            .block_by_number(fork_block_number - 1)
+            .await
+            .unwrap()
+            .unwrap();
+        let claim = CallClaim {
+            block_hash: earlier.hash.unwrap(),
+            ..claim
+        };
+        assert!(expected_instance(&claim, &prepared.bundle, &earlier).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    inputs_builder::{CallWitness, WitnessInputs},
+    overrides::StateOverride,
+    types::zkevm_types::H256,
+};
+
+/// Version of the witness file format, bumped on any incompatible change
+pub const WITNESS_FILE_VERSION: u32 = 1;
+
+/// A call's witness inputs written by `prove witness --out`, so that the
+/// witness can be proven with `prove --witness` on a machine without access
+/// to the node. The circuit parameters are left to the proving side, they
+/// have to match its keys anyway.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WitnessFile {
+    pub version: u32,
+    pub tx_hash: H256,
+    /// state assumed rather than proven, carried into the bundle
+    #[serde(default, skip_serializing_if = "StateOverride::is_empty")]
+    pub assumed_state: StateOverride,
+    /// signature to decode the return data with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    pub inputs: WitnessInputs,
+}
+
+/// Only the version, read before the rest of the file
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl WitnessFile {
+    pub fn new(
+        call_witness: &CallWitness,
+        assumed_state: StateOverride,
+        signature: Option<String>,
+    ) -> Self {
+        Self {
+            version: WITNESS_FILE_VERSION,
+            tx_hash: call_witness.tx.hash,
+            assumed_state,
+            signature,
+            inputs: call_witness.inputs.clone(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let bytes = fs::read(path)?;
+        let Version { version } = serde_json::from_slice(&bytes)?;
+        if version != WITNESS_FILE_VERSION {
+            return Err(Error::config(format!(
+                "{} has witness file version {version}, expected {WITNESS_FILE_VERSION}",
+                path.display()
+            )));
+        }
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Writes through a temporary file, so that an interrupted write never
+    /// leaves a truncated witness file behind
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WitnessFile;
+    #[cfg(feature = "anvil")]
+    use crate::{
+        call::{
+            tests::{local_session, STORAGE_READER},
+            CallRequest,
+        },
+        inputs_builder::CallWitness,
+        prover::build_circuit,
+        types::zkevm_types::Bytes,
+    };
+
+    #[test]
+    fn test_version() {
+        let path =
+            std::env::temp_dir().join(format!("zk_eth_call_witness_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"version":0,"txHash":"0x00"}"#).unwrap();
+        let err = WitnessFile::read(&path).unwrap_err();
+        assert!(err.to_string().contains("witness file version 0"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// A witness read back from its file is the witness it was written from
+    /// and builds a circuit with the same instance
+    #[cfg(feature = "anvil")]
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let mut session = local_session().await;
+        let request = CallRequest::new(STORAGE_READER, Bytes::default());
+        let mut call_witness = session.witness(&request).await.unwrap();
+        let assumed_state = session.builder.state_override.clone();
+
+        let path = std::env::temp_dir().join(format!(
+            "zk_eth_call_witness_roundtrip_{}.json",
+            std::process::id()
+        ));
+        WitnessFile::new(&call_witness, assumed_state.clone(), None)
+            .write(&path)
+            .unwrap();
+        let witness_file = WitnessFile::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(witness_file.tx_hash, call_witness.tx.hash);
+        assert_eq!(witness_file.assumed_state, assumed_state);
+
+        let mut read_witness = CallWitness::from_inputs(
+            witness_file.inputs,
+            witness_file.tx_hash,
+            session.builder.circuits_params,
+        )
+        .unwrap();
+        assert_eq!(read_witness.tx, call_witness.tx);
+        assert_eq!(read_witness.trace, call_witness.trace);
+        assert_eq!(
+            format!("{:?}", read_witness.witness.txs),
+            format!("{:?}", call_witness.witness.txs)
+        );
+        assert_eq!(
+            format!("{:?}", read_witness.witness.rws.table_assignments()),
+            format!("{:?}", call_witness.witness.rws.table_assignments())
+        );
+
+        let (_, instance) = build_circuit(&mut call_witness);
+        let (_, read_instance) = build_circuit(&mut read_witness);
+        assert_eq!(read_instance, instance);
+    }
+}